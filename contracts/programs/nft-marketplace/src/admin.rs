@@ -0,0 +1,130 @@
+use anchor_lang::prelude::*;
+use crate::{Marketplace, MarketplaceError, MAX_BASIS_POINTS};
+
+/// Emitted whenever the fee, keeper tip or treasury changes.
+#[event]
+pub struct MarketplaceConfigUpdated {
+    pub marketplace: Pubkey,
+    pub fee_basis_points: u16,
+    pub keeper_tip_basis_points: u16,
+    pub treasury: Pubkey,
+}
+
+/// Emitted when the current authority nominates a successor.
+#[event]
+pub struct AuthorityProposed {
+    pub marketplace: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+/// Emitted when the nominated authority takes over.
+#[event]
+pub struct AuthorityTransferred {
+    pub marketplace: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+/// Updates whichever of the fee, keeper tip and treasury are given. The
+/// fee and tip together may not exceed the whole price.
+pub fn update_marketplace_config(
+    ctx: Context<UpdateMarketplaceConfig>,
+    fee_basis_points: Option<u16>,
+    keeper_tip_basis_points: Option<u16>,
+    treasury: Option<Pubkey>,
+) -> Result<()> {
+    let marketplace = &mut ctx.accounts.marketplace;
+    let fee_basis_points = fee_basis_points.unwrap_or(marketplace.fee_basis_points);
+    let keeper_tip_basis_points =
+        keeper_tip_basis_points.unwrap_or(marketplace.keeper_tip_basis_points);
+    require!(
+        fee_basis_points as u32 + keeper_tip_basis_points as u32 <= MAX_BASIS_POINTS as u32,
+        MarketplaceError::InvalidFee
+    );
+
+    marketplace.fee_basis_points = fee_basis_points;
+    marketplace.keeper_tip_basis_points = keeper_tip_basis_points;
+    if let Some(treasury) = treasury {
+        marketplace.treasury = treasury;
+    }
+
+    emit!(MarketplaceConfigUpdated {
+        marketplace: marketplace.key(),
+        fee_basis_points,
+        keeper_tip_basis_points,
+        treasury: marketplace.treasury,
+    });
+    msg!("Marketplace config updated");
+    Ok(())
+}
+
+/// First half of an authority handover. Nothing changes until the proposed
+/// key signs `accept_authority`; proposing again replaces the nominee.
+pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+    let marketplace = &mut ctx.accounts.marketplace;
+    marketplace.pending_authority = Some(new_authority);
+
+    emit!(AuthorityProposed {
+        marketplace: marketplace.key(),
+        authority: marketplace.authority,
+        pending_authority: new_authority,
+    });
+    msg!("Authority handover proposed to {}", new_authority);
+    Ok(())
+}
+
+pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+    let marketplace = &mut ctx.accounts.marketplace;
+    let previous_authority = marketplace.authority;
+    marketplace.authority = ctx.accounts.new_authority.key();
+    marketplace.pending_authority = None;
+
+    emit!(AuthorityTransferred {
+        marketplace: marketplace.key(),
+        previous_authority,
+        new_authority: marketplace.authority,
+    });
+    msg!("Authority transferred to {}", marketplace.authority);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketplaceConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump,
+        has_one = authority @ MarketplaceError::UnauthorizedAuthority
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump,
+        has_one = authority @ MarketplaceError::UnauthorizedAuthority
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump,
+        constraint = marketplace.pending_authority == Some(new_authority.key())
+            @ MarketplaceError::NotPendingAuthority
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    pub new_authority: Signer<'info>,
+}
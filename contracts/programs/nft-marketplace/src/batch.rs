@@ -0,0 +1,320 @@
+use anchor_lang::prelude::*;
+use solana_program::compute_units::sol_remaining_compute_units;
+use anchor_lang::system_program::{self, CreateAccount};
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
+use anchor_spl::token::{self, CloseAccount, Token, TokenAccount, Transfer};
+use crate::{AssetStandard, Listing, Marketplace, MarketplaceError};
+
+/// Accounts per item passed to `list_many`: NFT mint, listing PDA, seller
+/// token account, escrow token account.
+pub const LIST_ITEM_ACCOUNTS: usize = 4;
+
+/// Accounts per item passed to `cancel_many`: listing, escrow token
+/// account, seller token account.
+pub const CANCEL_ITEM_ACCOUNTS: usize = 3;
+
+/// Compute budget kept in reserve per item; the batch stops once less than
+/// this remains.
+const COMPUTE_UNITS_PER_ITEM: u64 = 30_000;
+
+/// Outcome of one item in a `list_many` call.
+#[event]
+pub struct BatchListResult {
+    pub index: u16,
+    pub nft_mint: Pubkey,
+    pub price: u64,
+    pub success: bool,
+}
+
+/// Outcome of one item in a `cancel_many` call.
+#[event]
+pub struct BatchCancelResult {
+    pub index: u16,
+    pub listing: Pubkey,
+    pub success: bool,
+}
+
+/// Lists one unit of each mint at the matching entry in `prices`. Items
+/// that fail their checks are reported and skipped; nothing is escrowed
+/// for them.
+pub fn list_many<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ListMany<'info>>,
+    prices: Vec<u64>,
+    royalty_percentage: u16,
+) -> Result<()> {
+    let remaining = ctx.remaining_accounts;
+    require!(
+        remaining.len() == prices.len() * LIST_ITEM_ACCOUNTS && !prices.is_empty(),
+        MarketplaceError::InvalidBatchAccounts
+    );
+    require!(royalty_percentage <= 5000, MarketplaceError::InvalidRoyalty);
+
+    let now = Clock::get()?.unix_timestamp;
+    let marketplace_key = ctx.accounts.marketplace.key();
+    let seller = ctx.accounts.seller.to_account_info();
+    let mut listed: u16 = 0;
+
+    for (index, (item, price)) in remaining.chunks(LIST_ITEM_ACCOUNTS).zip(prices).enumerate() {
+        if sol_remaining_compute_units() < COMPUTE_UNITS_PER_ITEM {
+            msg!("Compute budget exhausted after {} item(s)", index);
+            break;
+        }
+
+        let nft_mint = &item[0];
+        let listing_info = &item[1];
+        let seller_nft_account = &item[2];
+        let escrow_nft_account = &item[3];
+
+        let bump = match check_list_item(item, marketplace_key, seller.key(), price) {
+            Ok(bump) => bump,
+            Err(err) => {
+                msg!("Item {} not listed: {}", index, err);
+                emit!(BatchListResult {
+                    index: index as u16,
+                    nft_mint: nft_mint.key(),
+                    price,
+                    success: false,
+                });
+                continue;
+            }
+        };
+
+        let mint_key = nft_mint.key();
+        let seeds = &[
+            b"listing",
+            marketplace_key.as_ref(),
+            mint_key.as_ref(),
+            &[bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Same account list_nft creates through `init`
+        let space = 8 + Listing::LEN;
+        let cpi_accounts = CreateAccount {
+            from: seller.clone(),
+            to: listing_info.clone(),
+        };
+        let cpi_program = ctx.accounts.system_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        system_program::create_account(
+            cpi_ctx,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &crate::ID,
+        )?;
+
+        let listing = Listing {
+            marketplace: marketplace_key,
+            seller: seller.key(),
+            nft_mint: mint_key,
+            price,
+            quantity: 1,
+            royalty_percentage,
+            royalty_recipient: seller.key(),
+            allowed_buyer: None,
+            is_active: true,
+            listed_at: now,
+            starts_at: now,
+            asset_standard: AssetStandard::Legacy,
+            version: 0,
+            bump,
+        };
+        listing.try_serialize(&mut &mut listing_info.try_borrow_mut_data()?[..])?;
+
+        let cpi_accounts = Create {
+            payer: seller.clone(),
+            associated_token: escrow_nft_account.clone(),
+            authority: listing_info.clone(),
+            mint: nft_mint.clone(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+        associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+
+        // Transfer NFT to escrow
+        let cpi_accounts = Transfer {
+            from: seller_nft_account.clone(),
+            to: escrow_nft_account.clone(),
+            authority: seller.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, 1)?;
+
+        emit!(BatchListResult {
+            index: index as u16,
+            nft_mint: mint_key,
+            price,
+            success: true,
+        });
+        listed += 1;
+    }
+
+    msg!("Listed {} NFT(s)", listed);
+    Ok(())
+}
+
+/// Cancels each listing in the remaining accounts and returns its escrowed
+/// units to the seller. Items that fail their checks are reported and
+/// skipped.
+pub fn cancel_many<'info>(ctx: Context<'_, '_, 'info, 'info, CancelMany<'info>>) -> Result<()> {
+    let remaining = ctx.remaining_accounts;
+    require!(
+        !remaining.is_empty() && remaining.len() % CANCEL_ITEM_ACCOUNTS == 0,
+        MarketplaceError::InvalidBatchAccounts
+    );
+
+    let seller = ctx.accounts.seller.to_account_info();
+    let mut cancelled: u16 = 0;
+
+    for (index, item) in remaining.chunks(CANCEL_ITEM_ACCOUNTS).enumerate() {
+        if sol_remaining_compute_units() < COMPUTE_UNITS_PER_ITEM {
+            msg!("Compute budget exhausted after {} item(s)", index);
+            break;
+        }
+
+        let listing_info = &item[0];
+        let escrow_nft_account = &item[1];
+        let seller_nft_account = &item[2];
+
+        let listing = match check_cancel_item(item, ctx.accounts.marketplace.key(), seller.key()) {
+            Ok(listing) => listing,
+            Err(err) => {
+                msg!("Item {} not cancelled: {}", index, err);
+                emit!(BatchCancelResult {
+                    index: index as u16,
+                    listing: listing_info.key(),
+                    success: false,
+                });
+                continue;
+            }
+        };
+
+        let seeds = &[
+            b"listing",
+            listing.marketplace.as_ref(),
+            listing.nft_mint.as_ref(),
+            &[listing.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Return NFT to seller
+        let cpi_accounts = Transfer {
+            from: escrow_nft_account.clone(),
+            to: seller_nft_account.clone(),
+            authority: listing_info.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, listing.quantity)?;
+
+        let cpi_accounts = CloseAccount {
+            account: escrow_nft_account.clone(),
+            destination: seller.clone(),
+            authority: listing_info.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+
+        listing.close(seller.clone())?;
+
+        emit!(BatchCancelResult {
+            index: index as u16,
+            listing: listing_info.key(),
+            success: true,
+        });
+        cancelled += 1;
+    }
+
+    msg!("Cancelled {} listing(s)", cancelled);
+    Ok(())
+}
+
+/// Validates a `list_many` item and returns the listing PDA bump.
+fn check_list_item<'info>(
+    item: &'info [AccountInfo<'info>],
+    marketplace: Pubkey,
+    seller: Pubkey,
+    price: u64,
+) -> Result<u8> {
+    require!(price > 0, MarketplaceError::InvalidPrice);
+
+    let nft_mint = item[0].key();
+    let (listing_key, bump) =
+        Pubkey::find_program_address(&[b"listing", marketplace.as_ref(), nft_mint.as_ref()], &crate::ID);
+    require_keys_eq!(item[1].key(), listing_key, MarketplaceError::InvalidBatchAccounts);
+    require!(item[1].lamports() == 0, MarketplaceError::InvalidBatchAccounts);
+
+    let seller_nft_account = Account::<TokenAccount>::try_from(&item[2])?;
+    require_keys_eq!(seller_nft_account.mint, nft_mint, MarketplaceError::InvalidBatchAccounts);
+    require_keys_eq!(seller_nft_account.owner, seller, MarketplaceError::InvalidBatchAccounts);
+    require!(seller_nft_account.amount >= 1, MarketplaceError::InvalidQuantity);
+
+    require_keys_eq!(
+        item[3].key(),
+        associated_token::get_associated_token_address(&listing_key, &nft_mint),
+        MarketplaceError::InvalidBatchAccounts
+    );
+    Ok(bump)
+}
+
+/// Validates a `cancel_many` item and returns the loaded listing.
+fn check_cancel_item<'info>(
+    item: &'info [AccountInfo<'info>],
+    marketplace: Pubkey,
+    seller: Pubkey,
+) -> Result<Account<'info, Listing>> {
+    let listing = Account::<Listing>::try_from(&item[0])?;
+    require_keys_eq!(listing.marketplace, marketplace, MarketplaceError::MarketplaceMismatch);
+    require_keys_eq!(listing.seller, seller, MarketplaceError::InvalidBatchAccounts);
+    require!(listing.is_active, MarketplaceError::ListingNotActive);
+    require!(
+        listing.asset_standard == AssetStandard::Legacy,
+        MarketplaceError::InvalidTokenStandard
+    );
+
+    let escrow_nft_account = Account::<TokenAccount>::try_from(&item[1])?;
+    require_keys_eq!(escrow_nft_account.mint, listing.nft_mint, MarketplaceError::InvalidBatchAccounts);
+    require_keys_eq!(escrow_nft_account.owner, listing.key(), MarketplaceError::InvalidBatchAccounts);
+    require!(
+        escrow_nft_account.amount == listing.quantity,
+        MarketplaceError::InvalidBatchAccounts
+    );
+
+    let seller_nft_account = Account::<TokenAccount>::try_from(&item[2])?;
+    require_keys_eq!(seller_nft_account.mint, listing.nft_mint, MarketplaceError::InvalidBatchAccounts);
+    require_keys_eq!(seller_nft_account.owner, seller, MarketplaceError::InvalidBatchAccounts);
+    Ok(listing)
+}
+
+#[derive(Accounts)]
+pub struct ListMany<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelMany<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
@@ -0,0 +1,456 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
+use anchor_spl::token::{self, CloseAccount, Token, TokenAccount, Transfer};
+use crate::metaplex_standards::metadata_creator_payouts;
+use crate::{calculate_fee, pay_from_buyer, seller_proceeds, Marketplace, MarketplaceError};
+
+pub const MAX_BUNDLE_SIZE: usize = 10;
+
+/// Accounts per item passed to `list_bundle`: mint, seller token account,
+/// escrow token account.
+const LIST_ACCOUNTS_PER_ITEM: usize = 3;
+
+/// Accounts per item passed to `cancel_bundle`: escrow token account,
+/// seller token account.
+const CANCEL_ACCOUNTS_PER_ITEM: usize = 2;
+
+/// A set of NFTs sold together at one price. Every item sits in an escrow
+/// token account owned by this PDA. `item_prices` follows `mints` and sums
+/// to `price`; see `item_royalty_basis` for what each royalty is taken on.
+#[account]
+pub struct BundleListing {
+    pub marketplace: Pubkey,
+    pub seller: Pubkey,
+    pub bundle_id: u64,
+    pub mints: Vec<Pubkey>,
+    pub item_prices: Vec<u64>,
+    pub price: u64,
+    pub is_active: bool,
+    pub listed_at: i64,
+    pub bump: u8,
+}
+
+impl BundleListing {
+    pub const LEN: usize =
+        32 + 32 + 8 + (4 + 32 * MAX_BUNDLE_SIZE) + (4 + 8 * MAX_BUNDLE_SIZE) + 8 + 1 + 8 + 1;
+}
+
+/// The bundle price: the sum of its item prices, each of which must be set.
+pub fn bundle_price(item_prices: &[u64]) -> Result<u64> {
+    require!(!item_prices.is_empty(), MarketplaceError::InvalidBundleAccounts);
+    item_prices.iter().try_fold(0u64, |total, item_price| {
+        require!(*item_price > 0, MarketplaceError::InvalidPrice);
+        Ok(total.checked_add(*item_price).ok_or(MarketplaceError::MathOverflow)?)
+    })
+}
+
+/// What an item's royalty is taken on: its listed price, but never less
+/// than an equal share of the bundle. Shifting price onto a low-royalty item
+/// therefore cannot shrink any other item's royalty below the equal split.
+pub fn item_royalty_basis(item_price: u64, bundle_price: u64, item_count: usize) -> u64 {
+    let equal_share = bundle_price / (item_count.max(1) as u64);
+    item_price.max(equal_share)
+}
+
+pub fn list_bundle<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ListBundle<'info>>,
+    bundle_id: u64,
+    item_prices: Vec<u64>,
+) -> Result<()> {
+    let price = bundle_price(&item_prices)?;
+
+    let remaining = ctx.remaining_accounts;
+    require!(
+        remaining.len() == item_prices.len() * LIST_ACCOUNTS_PER_ITEM,
+        MarketplaceError::InvalidBundleAccounts
+    );
+    let item_count = item_prices.len();
+    require!(item_count <= MAX_BUNDLE_SIZE, MarketplaceError::BundleTooLarge);
+
+    let bundle_info = ctx.accounts.bundle_listing.to_account_info();
+    let mut mints = Vec::with_capacity(item_count);
+
+    for item in remaining.chunks(LIST_ACCOUNTS_PER_ITEM) {
+        let (nft_mint, seller_nft_account, escrow_nft_account) = (&item[0], &item[1], &item[2]);
+
+        let seller_token = Account::<TokenAccount>::try_from(seller_nft_account)?;
+        require_keys_eq!(seller_token.mint, nft_mint.key(), MarketplaceError::InvalidBundleAccounts);
+        require_keys_eq!(seller_token.owner, ctx.accounts.seller.key(), MarketplaceError::InvalidBundleAccounts);
+        require!(seller_token.amount == 1, MarketplaceError::InvalidBundleAccounts);
+
+        // Create the bundle-owned escrow; fails unless it is the canonical ATA
+        let cpi_accounts = Create {
+            payer: ctx.accounts.seller.to_account_info(),
+            associated_token: escrow_nft_account.clone(),
+            authority: bundle_info.clone(),
+            mint: nft_mint.clone(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+        associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+
+        // Transfer NFT to escrow
+        let cpi_accounts = Transfer {
+            from: seller_nft_account.clone(),
+            to: escrow_nft_account.clone(),
+            authority: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, 1)?;
+
+        mints.push(nft_mint.key());
+    }
+
+    let bundle_listing = &mut ctx.accounts.bundle_listing;
+    bundle_listing.marketplace = ctx.accounts.marketplace.key();
+    bundle_listing.seller = ctx.accounts.seller.key();
+    bundle_listing.bundle_id = bundle_id;
+    bundle_listing.mints = mints;
+    bundle_listing.item_prices = item_prices;
+    bundle_listing.price = price;
+    bundle_listing.is_active = true;
+    bundle_listing.listed_at = Clock::get()?.unix_timestamp;
+    bundle_listing.bump = ctx.bumps.bundle_listing;
+
+    msg!("Bundle of {} NFTs listed for {} lamports", item_count, price);
+    Ok(())
+}
+
+/// Remaining accounts, per item in bundle order: mint, escrow token account,
+/// buyer token account, metadata, then one account per metadata creator.
+pub fn buy_bundle<'info>(ctx: Context<'_, '_, 'info, 'info, BuyBundle<'info>>) -> Result<()> {
+    let bundle_listing = &ctx.accounts.bundle_listing;
+    require!(bundle_listing.is_active, MarketplaceError::ListingNotActive);
+
+    let price = bundle_listing.price;
+    let mints = bundle_listing.mints.clone();
+    let item_prices = bundle_listing.item_prices.clone();
+    let item_count = mints.len() as u64;
+
+    let seller_key = bundle_listing.seller;
+    let bundle_id = bundle_listing.bundle_id.to_le_bytes();
+    let seeds = &[
+        b"bundle",
+        bundle_listing.marketplace.as_ref(),
+        seller_key.as_ref(),
+        bundle_id.as_ref(),
+        &[bundle_listing.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let bundle_info = ctx.accounts.bundle_listing.to_account_info();
+
+    let remaining = ctx.remaining_accounts;
+    let mut cursor = 0;
+    let mut royalty_paid: u64 = 0;
+
+    for (expected_mint, item_price) in mints.iter().zip(item_prices) {
+        require!(remaining.len() >= cursor + 4, MarketplaceError::InvalidBundleAccounts);
+        let nft_mint = &remaining[cursor];
+        let escrow_nft_account = &remaining[cursor + 1];
+        let buyer_nft_account = &remaining[cursor + 2];
+        let nft_metadata = &remaining[cursor + 3];
+        cursor += 4;
+
+        require_keys_eq!(nft_mint.key(), *expected_mint, MarketplaceError::InvalidBundleAccounts);
+
+        let basis = item_royalty_basis(item_price, price, mints.len());
+        let payouts =
+            metadata_creator_payouts(nft_metadata, expected_mint, basis, &remaining[cursor..])?;
+        cursor += payouts.len();
+
+        for (creator, amount) in payouts {
+            pay_from_buyer(&ctx.accounts.buyer, creator, &ctx.accounts.system_program, amount)?;
+            royalty_paid += amount;
+        }
+
+        let cpi_accounts = Create {
+            payer: ctx.accounts.buyer.to_account_info(),
+            associated_token: buyer_nft_account.clone(),
+            authority: ctx.accounts.buyer.to_account_info(),
+            mint: nft_mint.clone(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+        associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+
+        let cpi_accounts = Transfer {
+            from: escrow_nft_account.clone(),
+            to: buyer_nft_account.clone(),
+            authority: bundle_info.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        close_escrow(
+            &ctx.accounts.token_program,
+            escrow_nft_account,
+            &ctx.accounts.seller.to_account_info(),
+            &bundle_info,
+            signer,
+        )?;
+    }
+
+    // Split the payment once for the whole bundle
+    let marketplace_fee = calculate_fee(price, ctx.accounts.marketplace.fee_basis_points)?;
+    let seller_amount = seller_proceeds(price, &[marketplace_fee, royalty_paid])?;
+
+    pay_from_buyer(
+        &ctx.accounts.buyer,
+        &ctx.accounts.treasury,
+        &ctx.accounts.system_program,
+        marketplace_fee,
+    )?;
+    pay_from_buyer(
+        &ctx.accounts.buyer,
+        &ctx.accounts.seller.to_account_info(),
+        &ctx.accounts.system_program,
+        seller_amount,
+    )?;
+
+    ctx.accounts.bundle_listing.is_active = false;
+
+    let marketplace = &mut ctx.accounts.marketplace;
+    marketplace.total_sales += item_count;
+    marketplace.total_volume += price;
+
+    msg!("Bundle of {} NFTs sold for {} lamports", item_count, price);
+    Ok(())
+}
+
+/// Remaining accounts, per item in bundle order: escrow token account,
+/// seller token account.
+pub fn cancel_bundle<'info>(ctx: Context<'_, '_, 'info, 'info, CancelBundle<'info>>) -> Result<()> {
+    let bundle_listing = &ctx.accounts.bundle_listing;
+    let remaining = ctx.remaining_accounts;
+    require!(
+        remaining.len() == bundle_listing.mints.len() * CANCEL_ACCOUNTS_PER_ITEM,
+        MarketplaceError::InvalidBundleAccounts
+    );
+
+    let seller_key = bundle_listing.seller;
+    let bundle_id = bundle_listing.bundle_id.to_le_bytes();
+    let seeds = &[
+        b"bundle",
+        bundle_listing.marketplace.as_ref(),
+        seller_key.as_ref(),
+        bundle_id.as_ref(),
+        &[bundle_listing.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let bundle_info = ctx.accounts.bundle_listing.to_account_info();
+
+    for (item, expected_mint) in remaining.chunks(CANCEL_ACCOUNTS_PER_ITEM).zip(bundle_listing.mints.iter()) {
+        let (escrow_nft_account, seller_nft_account) = (&item[0], &item[1]);
+
+        let seller_token = Account::<TokenAccount>::try_from(seller_nft_account)?;
+        require_keys_eq!(seller_token.mint, *expected_mint, MarketplaceError::InvalidBundleAccounts);
+        require_keys_eq!(seller_token.owner, seller_key, MarketplaceError::InvalidBundleAccounts);
+
+        // Return NFT to seller
+        let cpi_accounts = Transfer {
+            from: escrow_nft_account.clone(),
+            to: seller_nft_account.clone(),
+            authority: bundle_info.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        close_escrow(
+            &ctx.accounts.token_program,
+            escrow_nft_account,
+            &ctx.accounts.seller.to_account_info(),
+            &bundle_info,
+            signer,
+        )?;
+    }
+
+    msg!("Bundle {} cancelled", bundle_listing.bundle_id);
+    Ok(())
+}
+
+fn close_escrow<'info>(
+    token_program: &Program<'info, Token>,
+    escrow_nft_account: &AccountInfo<'info>,
+    seller: &AccountInfo<'info>,
+    bundle_info: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    let cpi_accounts = CloseAccount {
+        account: escrow_nft_account.clone(),
+        destination: seller.clone(),
+        authority: bundle_info.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token::close_account(cpi_ctx)
+}
+
+#[derive(Accounts)]
+#[instruction(bundle_id: u64)]
+pub struct ListBundle<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + BundleListing::LEN,
+        seeds = [
+            b"bundle",
+            marketplace.key().as_ref(),
+            seller.key().as_ref(),
+            bundle_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub bundle_listing: Account<'info, BundleListing>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyBundle<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"bundle",
+            marketplace.key().as_ref(),
+            bundle_listing.seller.as_ref(),
+            bundle_listing.bundle_id.to_le_bytes().as_ref()
+        ],
+        bump = bundle_listing.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = bundle_listing.is_active,
+        close = seller
+    )]
+    pub bundle_listing: Account<'info, BundleListing>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mut, constraint = seller.key() == bundle_listing.seller)]
+    pub seller: SystemAccount<'info>,
+
+    /// CHECK: Treasury account
+    #[account(mut, constraint = treasury.key() == marketplace.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBundle<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"bundle",
+            marketplace.key().as_ref(),
+            bundle_listing.seller.as_ref(),
+            bundle_listing.bundle_id.to_le_bytes().as_ref()
+        ],
+        bump = bundle_listing.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = bundle_listing.seller == seller.key(),
+        constraint = bundle_listing.is_active,
+        close = seller
+    )]
+    pub bundle_listing: Account<'info, BundleListing>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn bundle_price_sums_item_prices() {
+        assert_eq!(bundle_price(&[1_000, 250, 5]).unwrap(), 1_255);
+    }
+
+    #[test]
+    fn bundle_price_rejects_unpriced_items() {
+        assert_eq!(
+            bundle_price(&[1_000, 0]).unwrap_err(),
+            MarketplaceError::InvalidPrice.into()
+        );
+        assert_eq!(
+            bundle_price(&[]).unwrap_err(),
+            MarketplaceError::InvalidBundleAccounts.into()
+        );
+    }
+
+    #[test]
+    fn bundle_price_rejects_overflow() {
+        assert_eq!(
+            bundle_price(&[u64::MAX, 1]).unwrap_err(),
+            MarketplaceError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn royalty_basis_is_floored_at_an_equal_share() {
+        // 990 of a 1_000 bundle put on one of three items: the other two
+        // still owe royalty on a third of the bundle each
+        let prices = [990, 5, 5];
+        let price = bundle_price(&prices).unwrap();
+        let bases: Vec<u64> = prices.iter().map(|p| item_royalty_basis(*p, price, 3)).collect();
+        assert_eq!(bases, vec![990, 333, 333]);
+    }
+
+    #[test]
+    fn royalty_basis_keeps_prices_above_an_equal_share() {
+        assert_eq!(item_royalty_basis(700, 1_000, 2), 700);
+        assert_eq!(item_royalty_basis(300, 1_000, 2), 500);
+    }
+
+    proptest! {
+        #[test]
+        fn royalty_basis_never_below_equal_share(
+            prices in prop::collection::vec(1..=u64::MAX / MAX_BUNDLE_SIZE as u64, 1..=MAX_BUNDLE_SIZE),
+        ) {
+            let price = bundle_price(&prices).unwrap();
+            let equal_share = price / prices.len() as u64;
+            let mut total: u128 = 0;
+            for item_price in &prices {
+                let basis = item_royalty_basis(*item_price, price, prices.len());
+                prop_assert!(basis >= equal_share);
+                prop_assert!(basis >= *item_price);
+                total += basis as u128;
+            }
+            // Royalties in total are taken on at least the bundle price
+            prop_assert!(total >= price as u128);
+        }
+    }
+}
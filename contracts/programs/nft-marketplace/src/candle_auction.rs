@@ -0,0 +1,758 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use crate::metaplex_standards::metadata_creator_payouts;
+use crate::{
+    calculate_fee, keeper_payout, pay_from_buyer, pay_from_escrow, resolve_start_time,
+    seller_proceeds, BidRefund, Marketplace, MarketplaceError,
+};
+
+/// The closing window is split into this many equal buckets. Only the last
+/// leading bid of each bucket is kept, which bounds the snapshot list no
+/// matter how many bids arrive, and the candle goes out at a bucket edge.
+pub const CANDLE_BUCKETS: usize = 32;
+
+/// Slots between committing to settle and the slot whose hash draws the
+/// effective end, so the hash is unknown to whoever commits.
+pub const CANDLE_COMMIT_DELAY_SLOTS: u64 = 8;
+
+/// Entries kept by the SlotHashes sysvar; older slot hashes cannot be read.
+const SLOT_HASHES_CAPACITY: u64 = 512;
+
+/// A new highest bid, recorded at the time it was placed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct CandleSnapshot {
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub refunded: bool,
+}
+
+impl CandleSnapshot {
+    pub const LEN: usize = 32 + 8 + 8 + 1;
+}
+
+/// Candle auction: bidding runs until `end_time`, but the effective end is
+/// drawn at settlement from somewhere inside the closing window, so a
+/// last-second bid may land after the candle already went out.
+#[account]
+pub struct CandleAuction {
+    pub marketplace: Pubkey,
+    pub seller: Pubkey,
+    pub nft_mint: Pubkey,
+    pub starting_price: u64,
+    pub current_bid: u64,
+    pub highest_bidder: Option<Pubkey>,
+    pub start_time: i64,
+    /// Start of the closing window in which snapshots are recorded
+    pub closing_start: i64,
+    pub end_time: i64,
+    /// Last leading bid of each closing-window bucket that saw a bid, oldest
+    /// first; bids from before the window count as the first bucket. All of
+    /// them stay escrowed until settlement
+    pub snapshots: Vec<CandleSnapshot>,
+    pub winning_snapshot: Option<u8>,
+    /// Slot whose hash draws the effective end, fixed once bidding is over
+    pub settle_slot: Option<u64>,
+    pub is_active: bool,
+    pub bump: u8,
+}
+
+impl CandleAuction {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + (1 + 32) + 8 + 8 + 8
+        + (4 + CandleSnapshot::LEN * CANDLE_BUCKETS)
+        + (1 + 1) + (1 + 8) + 1 + 1;
+
+    /// Closing-window bucket a bid placed at `timestamp` falls into.
+    fn bucket_at(&self, timestamp: i64) -> u64 {
+        let window = (self.end_time - self.closing_start) as u128;
+        let elapsed = (timestamp - self.closing_start).max(0) as u128;
+        (elapsed * CANDLE_BUCKETS as u128 / window) as u64
+    }
+
+    /// Index of the snapshot leading when the candle went out at the end of
+    /// `bucket`, if any bid had been placed by then.
+    fn winner_at(&self, bucket: u64) -> Option<usize> {
+        self.snapshots
+            .iter()
+            .rposition(|snapshot| self.bucket_at(snapshot.timestamp) <= bucket)
+    }
+
+    /// True once every losing snapshot has been refunded.
+    fn refunds_complete(&self) -> bool {
+        self.snapshots
+            .iter()
+            .enumerate()
+            .all(|(index, snapshot)| snapshot.refunded || self.winning_snapshot == Some(index as u8))
+    }
+}
+
+pub fn create_candle_auction(
+    ctx: Context<CreateCandleAuction>,
+    starting_price: u64,
+    duration: i64,
+    closing_window: i64,
+    starts_at: Option<i64>,
+) -> Result<()> {
+    require!(starting_price > 0, MarketplaceError::InvalidPrice);
+    require!(duration > 0, MarketplaceError::InvalidDuration);
+    require!(
+        closing_window > 0 && closing_window <= duration,
+        MarketplaceError::InvalidDuration
+    );
+
+    let start_time = resolve_start_time(starts_at, Clock::get()?.unix_timestamp)?;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.marketplace = ctx.accounts.marketplace.key();
+    auction.seller = ctx.accounts.seller.key();
+    auction.nft_mint = ctx.accounts.nft_mint.key();
+    auction.starting_price = starting_price;
+    auction.current_bid = 0;
+    auction.highest_bidder = None;
+    auction.start_time = start_time;
+    auction.closing_start = start_time + duration - closing_window;
+    auction.end_time = start_time + duration;
+    auction.snapshots = Vec::new();
+    auction.winning_snapshot = None;
+    auction.settle_slot = None;
+    auction.is_active = true;
+    auction.bump = ctx.bumps.auction;
+
+    // Transfer NFT to escrow
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.seller_nft_account.to_account_info(),
+        to: ctx.accounts.escrow_nft_account.to_account_info(),
+        authority: ctx.accounts.seller.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, 1)?;
+
+    msg!("Candle auction created with a {}s closing window", closing_window);
+    Ok(())
+}
+
+pub fn place_candle_bid(ctx: Context<PlaceCandleBid>, bid_amount: u64) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    let now = Clock::get()?.unix_timestamp;
+
+    require!(auction.is_active, MarketplaceError::AuctionNotActive);
+    require!(now >= auction.start_time, MarketplaceError::AuctionNotStarted);
+    require!(now < auction.end_time, MarketplaceError::AuctionEnded);
+    require!(bid_amount > auction.current_bid, MarketplaceError::BidTooLow);
+    require!(bid_amount >= auction.starting_price, MarketplaceError::BidBelowStarting);
+
+    // A bid outbid within its own bucket can never win, so it is released
+    let bucket = auction.bucket_at(now);
+    let replaces_leader = auction
+        .snapshots
+        .last()
+        .is_some_and(|snapshot| auction.bucket_at(snapshot.timestamp) == bucket);
+    require!(
+        replaces_leader == ctx.accounts.previous_bidder_refund.is_some(),
+        MarketplaceError::InvalidRefundAccount
+    );
+    if replaces_leader {
+        let previous_bidder = auction.highest_bidder.ok_or(MarketplaceError::InvalidWinner)?;
+        let refund = auction.current_bid;
+        let auction_info = auction.to_account_info();
+
+        let bid_refund = ctx
+            .accounts
+            .previous_bidder_refund
+            .as_mut()
+            .ok_or(MarketplaceError::InvalidRefundAccount)?;
+        bid_refund.bidder = previous_bidder;
+        bid_refund.auction = auction_info.key();
+        bid_refund.amount = bid_refund.amount.checked_add(refund).ok_or(MarketplaceError::MathOverflow)?;
+        bid_refund.bump = ctx.bumps.previous_bidder_refund.ok_or(MarketplaceError::InvalidRefundAccount)?;
+
+        pay_from_escrow(&auction_info, &bid_refund.to_account_info(), refund)?;
+    }
+
+    // Escrow new bid
+    pay_from_buyer(
+        &ctx.accounts.bidder,
+        &ctx.accounts.auction.to_account_info(),
+        &ctx.accounts.system_program,
+        bid_amount,
+    )?;
+
+    let snapshot = CandleSnapshot {
+        bidder: ctx.accounts.bidder.key(),
+        amount: bid_amount,
+        timestamp: now,
+        refunded: false,
+    };
+
+    let auction = &mut ctx.accounts.auction;
+    if replaces_leader {
+        auction.snapshots.pop();
+    }
+    auction.snapshots.push(snapshot);
+    auction.current_bid = bid_amount;
+    auction.highest_bidder = Some(ctx.accounts.bidder.key());
+
+    msg!("Candle bid placed for {} lamports", bid_amount);
+    Ok(())
+}
+
+/// Fixes the slot whose hash will draw the effective end. Anyone may call
+/// this once bidding is over; it can be called again only if the committed
+/// slot hash has aged out of the SlotHashes sysvar before settlement.
+pub fn commit_candle_settlement(ctx: Context<CommitCandleSettlement>) -> Result<()> {
+    let auction = &mut ctx.accounts.auction;
+    let clock = Clock::get()?;
+
+    require!(auction.is_active, MarketplaceError::AuctionNotActive);
+    require!(clock.unix_timestamp >= auction.end_time, MarketplaceError::AuctionNotEnded);
+    if let Some(settle_slot) = auction.settle_slot {
+        require!(
+            clock.slot >= settle_slot + SLOT_HASHES_CAPACITY,
+            MarketplaceError::CandleSlotNotReady
+        );
+    }
+
+    let settle_slot = clock.slot + CANDLE_COMMIT_DELAY_SLOTS;
+    auction.settle_slot = Some(settle_slot);
+
+    msg!("Candle auction will be drawn from slot {}", settle_slot);
+    Ok(())
+}
+
+/// Draws the effective end from the hash of the committed slot and pays out
+/// the bid that was leading at that moment. Remaining accounts are the NFT's
+/// creators in metadata order.
+pub fn settle_candle_auction<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SettleCandleAuction<'info>>,
+) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    let clock = Clock::get()?;
+
+    require!(auction.is_active, MarketplaceError::AuctionNotActive);
+    require!(clock.unix_timestamp >= auction.end_time, MarketplaceError::AuctionNotEnded);
+
+    // The slot was fixed after bidding closed, so nobody knew its hash while
+    // bids could still move. Its leader can only choose between its own hash
+    // and the next produced slot's by skipping the slot.
+    let settle_slot = auction.settle_slot.ok_or(MarketplaceError::CandleSlotNotReady)?;
+    require!(clock.slot > settle_slot, MarketplaceError::CandleSlotNotReady);
+    let seed = slot_hash_seed(&ctx.accounts.slot_hashes.try_borrow_data()?, settle_slot)?;
+
+    let bucket = seed % CANDLE_BUCKETS as u64;
+    let window = (auction.end_time - auction.closing_start) as u128;
+    let effective_end =
+        auction.closing_start + ((bucket as u128 + 1) * window / CANDLE_BUCKETS as u128) as i64;
+    let winning_snapshot = auction.winner_at(bucket);
+
+    let nft_mint = auction.nft_mint;
+    let seeds = &[
+        b"candle_auction",
+        auction.marketplace.as_ref(),
+        nft_mint.as_ref(),
+        &[auction.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    if let Some(index) = winning_snapshot {
+        let winning_bid = auction.snapshots[index];
+        require_keys_eq!(ctx.accounts.winner.key(), winning_bid.bidder, MarketplaceError::InvalidWinner);
+        let price = winning_bid.amount;
+
+        let marketplace_fee = calculate_fee(price, ctx.accounts.marketplace.fee_basis_points)?;
+        let payouts = metadata_creator_payouts(
+            &ctx.accounts.nft_metadata,
+            &nft_mint,
+            price,
+            ctx.remaining_accounts,
+        )?;
+
+        let mut royalty_paid: u64 = 0;
+        for (creator, amount) in payouts {
+            pay_from_escrow(&ctx.accounts.auction.to_account_info(), creator, amount)?;
+            royalty_paid += amount;
+        }
+
+        // The keeper pays for the winner's ATA if it does not exist yet
+        let ata_rent = if ctx.accounts.winner_nft_account.data_is_empty() {
+            Rent::get()?.minimum_balance(TokenAccount::LEN)
+        } else {
+            0
+        };
+        let cpi_accounts = Create {
+            payer: ctx.accounts.keeper.to_account_info(),
+            associated_token: ctx.accounts.winner_nft_account.to_account_info(),
+            authority: ctx.accounts.winner.to_account_info(),
+            mint: ctx.accounts.nft_mint.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+        associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+
+        let keeper_amount = keeper_payout(
+            price,
+            ctx.accounts.marketplace.keeper_tip_basis_points,
+            ata_rent,
+            &[marketplace_fee, royalty_paid],
+        )?;
+        let seller_amount = seller_proceeds(price, &[marketplace_fee, royalty_paid, keeper_amount])?;
+
+        pay_from_escrow(
+            &ctx.accounts.auction.to_account_info(),
+            &ctx.accounts.treasury.to_account_info(),
+            marketplace_fee,
+        )?;
+
+        pay_from_escrow(
+            &ctx.accounts.auction.to_account_info(),
+            &ctx.accounts.keeper.to_account_info(),
+            keeper_amount,
+        )?;
+
+        pay_from_escrow(
+            &ctx.accounts.auction.to_account_info(),
+            &ctx.accounts.seller.to_account_info(),
+            seller_amount,
+        )?;
+
+        // Transfer NFT to winner
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_nft_account.to_account_info(),
+            to: ctx.accounts.winner_nft_account.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        let marketplace = &mut ctx.accounts.marketplace;
+        marketplace.total_sales += 1;
+        marketplace.total_volume += price;
+
+        msg!("Candle went out at {} - NFT sold for {} lamports", effective_end, price);
+    } else {
+        // No bid had been placed by the effective end
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_nft_account.to_account_info(),
+            to: ctx.accounts.seller_nft_account.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        msg!("Candle went out at {} before any bid", effective_end);
+    }
+
+    let cpi_accounts = CloseAccount {
+        account: ctx.accounts.escrow_nft_account.to_account_info(),
+        destination: ctx.accounts.seller.to_account_info(),
+        authority: ctx.accounts.auction.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::close_account(cpi_ctx)?;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.winning_snapshot = winning_snapshot.map(|index| index as u8);
+    auction.is_active = false;
+
+    // Nothing left to refund, so the auction can be closed right away
+    if auction.refunds_complete() {
+        ctx.accounts.auction.close(ctx.accounts.seller.to_account_info())?;
+    }
+    Ok(())
+}
+
+/// Returns a losing snapshot's escrowed bid to its bidder once the auction
+/// is settled. The last refund closes the auction account.
+pub fn claim_candle_refund(ctx: Context<ClaimCandleRefund>, snapshot_index: u8) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    require!(!auction.is_active, MarketplaceError::AuctionNotEnded);
+    require!(
+        auction.winning_snapshot != Some(snapshot_index),
+        MarketplaceError::InvalidWinner
+    );
+
+    let snapshot = *auction
+        .snapshots
+        .get(snapshot_index as usize)
+        .ok_or(MarketplaceError::InvalidAuctionAccounts)?;
+    require_keys_eq!(snapshot.bidder, ctx.accounts.bidder.key(), MarketplaceError::InvalidWinner);
+    require!(!snapshot.refunded, MarketplaceError::RefundAlreadyClaimed);
+
+    pay_from_escrow(
+        &ctx.accounts.auction.to_account_info(),
+        &ctx.accounts.bidder.to_account_info(),
+        snapshot.amount,
+    )?;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.snapshots[snapshot_index as usize].refunded = true;
+
+    if auction.refunds_complete() {
+        ctx.accounts.auction.close(ctx.accounts.seller.to_account_info())?;
+    }
+
+    msg!("Refunded {} lamports from the candle auction", snapshot.amount);
+    Ok(())
+}
+
+/// First eight bytes of the hash of `slot` in raw SlotHashes sysvar data,
+/// or of the first slot produced after it if `slot` was skipped.
+fn slot_hash_seed(data: &[u8], slot: u64) -> Result<u64> {
+    // Layout: entry count (u64), then (slot: u64, hash: [u8; 32]) entries
+    // from newest to oldest
+    const ENTRY_LEN: usize = 8 + 32;
+    let count = usize::try_from(read_u64(data, 0)?).map_err(|_| MarketplaceError::InvalidAuctionAccounts)?;
+    let entries_end = count
+        .checked_mul(ENTRY_LEN)
+        .and_then(|len| len.checked_add(8))
+        .ok_or(MarketplaceError::InvalidAuctionAccounts)?;
+    let entries = data
+        .get(8..entries_end)
+        .ok_or(MarketplaceError::InvalidAuctionAccounts)?;
+
+    let mut seed = None;
+    for entry in entries.chunks(ENTRY_LEN) {
+        let entry_slot = read_u64(entry, 0)?;
+        if entry_slot < slot {
+            // Walked past the committed slot; the newer entry seen last wins
+            return seed.ok_or_else(|| MarketplaceError::CandleSlotNotReady.into());
+        }
+        let entry_seed = read_u64(entry, 8)?;
+        if entry_slot == slot {
+            return Ok(entry_seed);
+        }
+        seed = Some(entry_seed);
+    }
+    // Every entry is newer than the committed slot, so its hash has aged out
+    err!(MarketplaceError::CandleSlotExpired)
+}
+
+/// Little-endian u64 at `offset`, failing on short sysvar data.
+fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    let bytes = data
+        .get(offset..offset.saturating_add(8))
+        .ok_or(MarketplaceError::InvalidAuctionAccounts)?;
+    let bytes = <[u8; 8]>::try_from(bytes).map_err(|_| MarketplaceError::InvalidAuctionAccounts)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[derive(Accounts)]
+pub struct CreateCandleAuction<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + CandleAuction::LEN,
+        seeds = [b"candle_auction", marketplace.key().as_ref(), nft_mint.key().as_ref()],
+        bump
+    )]
+    pub auction: Account<'info, CandleAuction>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub nft_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == nft_mint.key(),
+        constraint = seller_nft_account.owner == seller.key(),
+        constraint = seller_nft_account.amount == 1
+    )]
+    pub seller_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = nft_mint,
+        associated_token::authority = auction
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceCandleBid<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"candle_auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub auction: Account<'info, CandleAuction>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    /// CHECK: Current highest bidder, or the bidder itself on the first bid
+    #[account(
+        constraint = auction.highest_bidder.is_none_or(|bidder| bidder == previous_bidder.key())
+            @ MarketplaceError::InvalidWinner
+    )]
+    pub previous_bidder: AccountInfo<'info>,
+
+    /// Refund account of the bidder replaced within the current bucket;
+    /// omitted when the bid opens a new bucket
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        space = 8 + BidRefund::LEN,
+        seeds = [b"bid_refund", auction.key().as_ref(), previous_bidder.key().as_ref()],
+        bump
+    )]
+    pub previous_bidder_refund: Option<Account<'info, BidRefund>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitCandleSettlement<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"candle_auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub auction: Account<'info, CandleAuction>,
+}
+
+#[derive(Accounts)]
+pub struct SettleCandleAuction<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"candle_auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub auction: Account<'info, CandleAuction>,
+
+    #[account(mut, constraint = seller.key() == auction.seller)]
+    pub seller: SystemAccount<'info>,
+
+    /// CHECK: Bidder leading at the drawn end, checked in the handler
+    pub winner: AccountInfo<'info>,
+
+    /// Whoever runs settlement; fronts the winner's ATA rent on a sale and is
+    /// repaid it along with the keeper tip
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(address = auction.nft_mint)]
+    pub nft_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = auction
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Winner's ATA, created by the keeper on a sale if missing
+    #[account(
+        mut,
+        address = associated_token::get_associated_token_address(&winner.key(), &auction.nft_mint)
+    )]
+    pub winner_nft_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == auction.nft_mint,
+        constraint = seller_nft_account.owner == seller.key()
+    )]
+    pub seller_nft_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Metadata PDA of the auctioned mint
+    #[account(
+        seeds = [b"metadata", mpl_token_metadata::ID.as_ref(), auction.nft_mint.as_ref()],
+        bump,
+        seeds::program = mpl_token_metadata::ID
+    )]
+    pub nft_metadata: AccountInfo<'info>,
+
+    /// CHECK: Treasury account
+    #[account(mut, constraint = treasury.key() == marketplace.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK: SlotHashes sysvar, read directly since it is too large to deserialize
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCandleRefund<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"candle_auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub auction: Account<'info, CandleAuction>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    #[account(mut, constraint = seller.key() == auction.seller)]
+    pub seller: SystemAccount<'info>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auction(closing_start: i64, end_time: i64, timestamps: &[i64]) -> CandleAuction {
+        CandleAuction {
+            marketplace: Pubkey::default(),
+            seller: Pubkey::default(),
+            nft_mint: Pubkey::default(),
+            starting_price: 1,
+            current_bid: 0,
+            highest_bidder: None,
+            start_time: 0,
+            closing_start,
+            end_time,
+            snapshots: timestamps
+                .iter()
+                .map(|&timestamp| CandleSnapshot {
+                    bidder: Pubkey::new_unique(),
+                    amount: 1,
+                    timestamp,
+                    refunded: false,
+                })
+                .collect(),
+            winning_snapshot: None,
+            settle_slot: None,
+            is_active: true,
+            bump: 0,
+        }
+    }
+
+    fn slot_hashes(entries: &[(u64, u64)]) -> Vec<u8> {
+        let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+        for (slot, seed) in entries {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&seed.to_le_bytes());
+            data.extend_from_slice(&[0; 24]);
+        }
+        data
+    }
+
+    #[test]
+    fn buckets_cover_the_closing_window() {
+        let auction = auction(1_000, 1_320, &[]);
+        assert_eq!(auction.bucket_at(0), 0);
+        assert_eq!(auction.bucket_at(1_000), 0);
+        assert_eq!(auction.bucket_at(1_009), 0);
+        assert_eq!(auction.bucket_at(1_010), 1);
+        assert_eq!(auction.bucket_at(1_319), CANDLE_BUCKETS as u64 - 1);
+    }
+
+    #[test]
+    fn short_window_still_maps_into_buckets() {
+        let auction = auction(1_000, 1_003, &[]);
+        assert_eq!(auction.bucket_at(1_000), 0);
+        assert_eq!(auction.bucket_at(1_002), 21);
+    }
+
+    #[test]
+    fn winner_is_last_snapshot_at_or_before_drawn_bucket() {
+        // Pre-window leader, then leaders in buckets 3 and 10
+        let auction = auction(1_000, 1_320, &[900, 1_035, 1_100]);
+        assert_eq!(auction.winner_at(0), Some(0));
+        assert_eq!(auction.winner_at(2), Some(0));
+        assert_eq!(auction.winner_at(3), Some(1));
+        assert_eq!(auction.winner_at(9), Some(1));
+        assert_eq!(auction.winner_at(31), Some(2));
+    }
+
+    #[test]
+    fn no_winner_before_first_bid() {
+        let auction = auction(1_000, 1_320, &[1_100]);
+        assert_eq!(auction.winner_at(9), None);
+        assert_eq!(auction.winner_at(10), Some(0));
+    }
+
+    #[test]
+    fn reads_hash_of_committed_slot() {
+        let data = slot_hashes(&[(105, 5), (104, 4), (103, 3)]);
+        assert_eq!(slot_hash_seed(&data, 104).unwrap(), 4);
+        assert_eq!(slot_hash_seed(&data, 105).unwrap(), 5);
+        assert_eq!(slot_hash_seed(&data, 103).unwrap(), 3);
+    }
+
+    #[test]
+    fn skipped_slot_uses_next_produced_slot() {
+        let data = slot_hashes(&[(107, 7), (105, 5), (102, 2)]);
+        assert_eq!(slot_hash_seed(&data, 103).unwrap(), 5);
+        assert_eq!(slot_hash_seed(&data, 106).unwrap(), 7);
+    }
+
+    #[test]
+    fn rejects_future_and_expired_slots() {
+        let data = slot_hashes(&[(105, 5), (104, 4)]);
+        assert_eq!(
+            slot_hash_seed(&data, 106).unwrap_err(),
+            MarketplaceError::CandleSlotNotReady.into()
+        );
+        assert_eq!(
+            slot_hash_seed(&data, 100).unwrap_err(),
+            MarketplaceError::CandleSlotExpired.into()
+        );
+    }
+
+    #[test]
+    fn rejects_entry_count_beyond_data() {
+        let mut data = slot_hashes(&[(105, 5)]);
+        data[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(
+            slot_hash_seed(&data, 105).unwrap_err(),
+            MarketplaceError::InvalidAuctionAccounts.into()
+        );
+        assert_eq!(
+            slot_hash_seed(&[0; 4], 105).unwrap_err(),
+            MarketplaceError::InvalidAuctionAccounts.into()
+        );
+    }
+}
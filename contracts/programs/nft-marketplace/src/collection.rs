@@ -0,0 +1,263 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount};
+use mpl_token_metadata::{
+    instructions::{
+        CreateMasterEditionV3Cpi, CreateMasterEditionV3CpiAccounts,
+        CreateMasterEditionV3InstructionArgs, CreateMetadataAccountV3Cpi,
+        CreateMetadataAccountV3CpiAccounts, CreateMetadataAccountV3InstructionArgs,
+        VerifySizedCollectionItemCpi, VerifySizedCollectionItemCpiAccounts,
+    },
+    types::{CollectionDetails, DataV2},
+};
+
+/// PDA that holds mint and update authority over a collection NFT, so that
+/// items can be verified into the collection by the program on behalf of
+/// `authority`.
+#[account]
+pub struct CollectionAuthority {
+    pub authority: Pubkey,
+    pub collection_mint: Pubkey,
+    pub bump: u8,
+}
+
+impl CollectionAuthority {
+    pub const LEN: usize = 32 + 32 + 1;
+}
+
+pub fn create_collection(
+    ctx: Context<CreateCollection>,
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+) -> Result<()> {
+    let collection_authority = &mut ctx.accounts.collection_authority;
+    collection_authority.authority = ctx.accounts.authority.key();
+    collection_authority.collection_mint = ctx.accounts.collection_mint.key();
+    collection_authority.bump = ctx.bumps.collection_authority;
+
+    let collection_mint_key = ctx.accounts.collection_mint.key();
+    let seeds = &[
+        b"collection_authority",
+        collection_mint_key.as_ref(),
+        &[ctx.bumps.collection_authority],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Mint the collection NFT to the authority
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.collection_mint.to_account_info(),
+        to: ctx.accounts.collection_token_account.to_account_info(),
+        authority: ctx.accounts.collection_authority.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::mint_to(cpi_ctx, 1)?;
+
+    let data = DataV2 {
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        creators: None,
+        collection: None,
+        uses: None,
+    };
+
+    let token_metadata_program = ctx.accounts.token_metadata_program.to_account_info();
+    let metadata = ctx.accounts.metadata.to_account_info();
+    let master_edition = ctx.accounts.master_edition.to_account_info();
+    let mint = ctx.accounts.collection_mint.to_account_info();
+    let authority = ctx.accounts.collection_authority.to_account_info();
+    let payer = ctx.accounts.payer.to_account_info();
+    let token_program = ctx.accounts.token_program.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    let rent = ctx.accounts.rent.to_account_info();
+
+    // Sized collections start at zero and are incremented on every verification
+    CreateMetadataAccountV3Cpi::new(
+        &token_metadata_program,
+        CreateMetadataAccountV3CpiAccounts {
+            metadata: &metadata,
+            mint: &mint,
+            mint_authority: &authority,
+            payer: &payer,
+            update_authority: (&authority, true),
+            system_program: &system_program,
+            rent: Some(&rent),
+        },
+        CreateMetadataAccountV3InstructionArgs {
+            data,
+            is_mutable: true,
+            collection_details: Some(CollectionDetails::V1 { size: 0 }),
+        },
+    )
+    .invoke_signed(signer)?;
+
+    CreateMasterEditionV3Cpi::new(
+        &token_metadata_program,
+        CreateMasterEditionV3CpiAccounts {
+            edition: &master_edition,
+            mint: &mint,
+            update_authority: &authority,
+            mint_authority: &authority,
+            payer: &payer,
+            metadata: &metadata,
+            token_program: &token_program,
+            system_program: &system_program,
+            rent: Some(&rent),
+        },
+        CreateMasterEditionV3InstructionArgs { max_supply: Some(0) },
+    )
+    .invoke_signed(signer)?;
+
+    msg!("Sized collection created: {}", collection_mint_key);
+    Ok(())
+}
+
+pub fn verify_collection_item(ctx: Context<VerifyCollectionItem>) -> Result<()> {
+    let collection_mint_key = ctx.accounts.collection_mint.key();
+    let seeds = &[
+        b"collection_authority",
+        collection_mint_key.as_ref(),
+        &[ctx.accounts.collection_authority.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let token_metadata_program = ctx.accounts.token_metadata_program.to_account_info();
+    let collection_authority = ctx.accounts.collection_authority.to_account_info();
+    let payer = ctx.accounts.payer.to_account_info();
+    let collection_mint = ctx.accounts.collection_mint.to_account_info();
+
+    VerifySizedCollectionItemCpi::new(
+        &token_metadata_program,
+        VerifySizedCollectionItemCpiAccounts {
+            metadata: &ctx.accounts.item_metadata,
+            collection_authority: &collection_authority,
+            payer: &payer,
+            collection_mint: &collection_mint,
+            collection: &ctx.accounts.collection_metadata,
+            collection_master_edition_account: &ctx.accounts.collection_master_edition,
+            collection_authority_record: None,
+        },
+    )
+    .invoke_signed(signer)?;
+
+    msg!("Item verified into collection {}", collection_mint_key);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CreateCollection<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + CollectionAuthority::LEN,
+        seeds = [b"collection_authority", collection_mint.key().as_ref()],
+        bump
+    )]
+    pub collection_authority: Account<'info, CollectionAuthority>,
+
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = collection_authority,
+        mint::freeze_authority = collection_authority
+    )]
+    pub collection_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = collection_mint,
+        associated_token::authority = authority
+    )]
+    pub collection_token_account: Account<'info, TokenAccount>,
+
+    /// Account allowed to verify items into the collection
+    pub authority: Signer<'info>,
+
+    /// CHECK: Metadata PDA, created by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub metadata: AccountInfo<'info>,
+
+    /// CHECK: Master edition PDA, created by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            collection_mint.key().as_ref(),
+            b"edition"
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub master_edition: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Token Metadata program
+    #[account(address = mpl_token_metadata::ID)]
+    pub token_metadata_program: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyCollectionItem<'info> {
+    #[account(
+        seeds = [b"collection_authority", collection_mint.key().as_ref()],
+        bump = collection_authority.bump,
+        constraint = collection_authority.authority == authority.key()
+    )]
+    pub collection_authority: Account<'info, CollectionAuthority>,
+
+    pub authority: Signer<'info>,
+
+    pub collection_mint: Account<'info, Mint>,
+
+    /// CHECK: Metadata of the collection NFT, its size is updated by Token Metadata
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_metadata: AccountInfo<'info>,
+
+    /// CHECK: Master edition of the collection NFT
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            collection_mint.key().as_ref(),
+            b"edition"
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_master_edition: AccountInfo<'info>,
+
+    /// CHECK: Metadata of the item being verified, validated by Token Metadata
+    #[account(mut, owner = mpl_token_metadata::ID)]
+    pub item_metadata: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Token Metadata program
+    #[account(address = mpl_token_metadata::ID)]
+    pub token_metadata_program: AccountInfo<'info>,
+}
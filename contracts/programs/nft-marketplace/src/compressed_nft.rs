@@ -1,7 +1,9 @@
 use anchor_lang::prelude::*;
 use mpl_bubblegum::{
-    instructions::MintV1Cpi,
-    state::MetadataArgs,
+    instructions::{MintV1Cpi, MintV1CpiAccounts, MintV1InstructionArgs},
+    types::{
+        Collection, Creator, MetadataArgs, TokenProgramVersion, TokenStandard, UseMethod, Uses,
+    },
 };
 use crate::compression::{CompressedNFTMetadata, CompressionConfig};
 
@@ -9,8 +11,6 @@ pub fn mint_compressed_nft(
     ctx: Context<MintCompressedNFT>,
     metadata: CompressedNFTMetadata,
 ) -> Result<()> {
-    let compression_config = &ctx.accounts.compression_config;
-    
     // Convert to Bubblegum metadata format
     let metadata_args = MetadataArgs {
         name: metadata.name,
@@ -20,48 +20,52 @@ pub fn mint_compressed_nft(
         primary_sale_happened: metadata.primary_sale_happened,
         is_mutable: metadata.is_mutable,
         edition_nonce: metadata.edition_nonce,
-        token_standard: Some(mpl_token_metadata::state::TokenStandard::NonFungible),
-        collection: metadata.collection.map(|c| mpl_bubblegum::state::Collection {
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: metadata.collection.map(|c| Collection {
             verified: c.verified,
             key: c.key,
         }),
-        uses: metadata.uses.map(|u| mpl_bubblegum::state::Uses {
+        uses: metadata.uses.map(|u| Uses {
             use_method: match u.use_type {
-                crate::compression::UseMethod::Burn => mpl_bubblegum::state::UseMethod::Burn,
-                crate::compression::UseMethod::Multiple => mpl_bubblegum::state::UseMethod::Multiple,
-                crate::compression::UseMethod::Single => mpl_bubblegum::state::UseMethod::Single,
+                crate::compression::UseMethod::Burn => UseMethod::Burn,
+                crate::compression::UseMethod::Multiple => UseMethod::Multiple,
+                crate::compression::UseMethod::Single => UseMethod::Single,
             },
             remaining: u.remaining,
             total: u.total,
         }),
-        creators: metadata.creators.map(|creators| {
-            creators.into_iter().map(|c| mpl_bubblegum::state::Creator {
+        creators: metadata
+            .creators
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| Creator {
                 address: c.address,
                 verified: c.verified,
                 share: c.share,
-            }).collect()
-        }),
-        token_program_version: mpl_bubblegum::state::TokenProgramVersion::Original,
+            })
+            .collect(),
+        token_program_version: TokenProgramVersion::Original,
     };
 
     // Mint compressed NFT using Bubblegum
-    let mint_ix = MintV1Cpi::new(
+    MintV1Cpi::new(
         &ctx.accounts.bubblegum_program,
         MintV1CpiAccounts {
-            tree_config: ctx.accounts.tree_config.to_account_info(),
-            leaf_owner: ctx.accounts.recipient.to_account_info(),
-            leaf_delegate: ctx.accounts.recipient.to_account_info(),
-            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
-            payer: ctx.accounts.payer.to_account_info(),
-            tree_creator_or_delegate: ctx.accounts.authority.to_account_info(),
-            log_wrapper: ctx.accounts.log_wrapper.to_account_info(),
-            compression_program: ctx.accounts.compression_program.to_account_info(),
-            system_program: ctx.accounts.system_program.to_account_info(),
+            tree_config: &ctx.accounts.tree_config,
+            leaf_owner: &ctx.accounts.recipient,
+            leaf_delegate: &ctx.accounts.recipient,
+            merkle_tree: &ctx.accounts.merkle_tree,
+            payer: &ctx.accounts.payer.to_account_info(),
+            tree_creator_or_delegate: &ctx.accounts.authority.to_account_info(),
+            log_wrapper: &ctx.accounts.log_wrapper,
+            compression_program: &ctx.accounts.compression_program,
+            system_program: &ctx.accounts.system_program.to_account_info(),
         },
-        metadata_args,
-    );
-
-    mint_ix.invoke()?;
+        MintV1InstructionArgs {
+            metadata: metadata_args,
+        },
+    )
+    .invoke()?;
 
     msg!("Compressed NFT minted successfully with 1000x cheaper storage");
     Ok(())
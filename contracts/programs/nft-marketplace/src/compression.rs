@@ -1,8 +1,4 @@
 use anchor_lang::prelude::*;
-use mpl_bubblegum::{
-    instructions::CreateTreeConfigCpi,
-    state::TreeConfig,
-};
 
 #[account]
 pub struct CompressionConfig {
@@ -0,0 +1,650 @@
+use anchor_lang::prelude::*;
+use mpl_core::{
+    accounts::{BaseAssetV1, BaseCollectionV1},
+    fetch_plugin,
+    instructions::TransferV1CpiBuilder,
+    types::{PluginType, Royalties, RuleSet},
+};
+use crate::{
+    calculate_fee, keeper_payout, pay_from_buyer, pay_from_escrow, percentage_share,
+    resolve_start_time, seller_proceeds, AssetStandard, Auction, Listing, Marketplace, MarketplaceError,
+};
+
+pub fn list_core_asset(ctx: Context<ListCoreAsset>, price: u64) -> Result<()> {
+    require!(price > 0, MarketplaceError::InvalidPrice);
+
+    let asset = BaseAssetV1::from_bytes(&ctx.accounts.asset.try_borrow_data()?)
+        .map_err(|_| MarketplaceError::InvalidCoreAsset)?;
+    require_keys_eq!(asset.owner, ctx.accounts.seller.key(), MarketplaceError::InvalidCoreAsset);
+
+    let royalties = fetch_core_royalties(&ctx.accounts.asset, ctx.accounts.collection.as_ref());
+    check_royalty_rule_set(royalties.as_ref())?;
+
+    // Core assets are single accounts, so the listing PDA takes ownership directly
+    TransferV1CpiBuilder::new(&ctx.accounts.mpl_core_program)
+        .asset(&ctx.accounts.asset)
+        .collection(ctx.accounts.collection.as_ref())
+        .payer(&ctx.accounts.seller.to_account_info())
+        .authority(Some(&ctx.accounts.seller.to_account_info()))
+        .new_owner(&ctx.accounts.listing.to_account_info())
+        .system_program(Some(&ctx.accounts.system_program.to_account_info()))
+        .invoke()?;
+
+    let listing = &mut ctx.accounts.listing;
+    listing.marketplace = ctx.accounts.marketplace.key();
+    listing.seller = ctx.accounts.seller.key();
+    listing.nft_mint = ctx.accounts.asset.key();
+    listing.price = price;
+    listing.quantity = 1;
+    listing.royalty_percentage = royalties.map_or(0, |r| r.basis_points);
+    listing.royalty_recipient = ctx.accounts.seller.key();
+    listing.allowed_buyer = None;
+    listing.is_active = true;
+    listing.listed_at = Clock::get()?.unix_timestamp;
+    listing.starts_at = listing.listed_at;
+    listing.asset_standard = AssetStandard::Core;
+    listing.version = 0;
+    listing.bump = ctx.bumps.listing;
+
+    msg!("Core asset listed for {} lamports", price);
+    Ok(())
+}
+
+pub fn buy_core_asset<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BuyCoreAsset<'info>>,
+) -> Result<()> {
+    let listing = &ctx.accounts.listing;
+    require!(listing.is_active, MarketplaceError::ListingNotActive);
+    require!(
+        listing.asset_standard == AssetStandard::Core,
+        MarketplaceError::InvalidTokenStandard
+    );
+
+    let price = listing.price;
+    let marketplace_fee = calculate_fee(price, ctx.accounts.marketplace.fee_basis_points)?;
+
+    // Royalties come from the Core `Royalties` plugin, creators are passed
+    // as remaining accounts in plugin order
+    let royalties = fetch_core_royalties(&ctx.accounts.asset, ctx.accounts.collection.as_ref());
+    let payouts = core_creator_payouts(royalties.as_ref(), price, ctx.remaining_accounts)?;
+
+    let mut royalty_paid: u64 = 0;
+    for (creator, amount) in payouts {
+        pay_from_buyer(&ctx.accounts.buyer, creator, &ctx.accounts.system_program, amount)?;
+        royalty_paid += amount;
+    }
+
+    let seller_amount = seller_proceeds(price, &[marketplace_fee, royalty_paid])?;
+
+    // Transfer marketplace fee to treasury
+    pay_from_buyer(
+        &ctx.accounts.buyer,
+        &ctx.accounts.treasury,
+        &ctx.accounts.system_program,
+        marketplace_fee,
+    )?;
+
+    // Transfer remaining to seller
+    pay_from_buyer(
+        &ctx.accounts.buyer,
+        &ctx.accounts.seller.to_account_info(),
+        &ctx.accounts.system_program,
+        seller_amount,
+    )?;
+
+    // Release the asset from the listing PDA to the buyer
+    let marketplace_key = ctx.accounts.marketplace.key();
+    let asset_key = ctx.accounts.asset.key();
+    let seeds = &[
+        b"listing",
+        marketplace_key.as_ref(),
+        asset_key.as_ref(),
+        &[listing.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    TransferV1CpiBuilder::new(&ctx.accounts.mpl_core_program)
+        .asset(&ctx.accounts.asset)
+        .collection(ctx.accounts.collection.as_ref())
+        .payer(&ctx.accounts.buyer.to_account_info())
+        .authority(Some(&ctx.accounts.listing.to_account_info()))
+        .new_owner(&ctx.accounts.buyer.to_account_info())
+        .system_program(Some(&ctx.accounts.system_program.to_account_info()))
+        .invoke_signed(signer)?;
+
+    let marketplace = &mut ctx.accounts.marketplace;
+    marketplace.total_sales += 1;
+    marketplace.total_volume += price;
+
+    msg!("Core asset sold for {} lamports", price);
+    Ok(())
+}
+
+pub fn create_core_auction(
+    ctx: Context<CreateCoreAuction>,
+    starting_price: u64,
+    reserve_price: u64,
+    duration: i64,
+    starts_at: Option<i64>,
+) -> Result<()> {
+    require!(starting_price > 0, MarketplaceError::InvalidPrice);
+    require!(reserve_price >= starting_price, MarketplaceError::InvalidReservePrice);
+    require!(duration > 0, MarketplaceError::InvalidDuration);
+
+    let start_time = resolve_start_time(starts_at, Clock::get()?.unix_timestamp)?;
+
+    let asset = BaseAssetV1::from_bytes(&ctx.accounts.asset.try_borrow_data()?)
+        .map_err(|_| MarketplaceError::InvalidCoreAsset)?;
+    require_keys_eq!(asset.owner, ctx.accounts.seller.key(), MarketplaceError::InvalidCoreAsset);
+
+    let royalties = fetch_core_royalties(&ctx.accounts.asset, ctx.accounts.collection.as_ref());
+    check_royalty_rule_set(royalties.as_ref())?;
+
+    // Move the asset into the auction PDA's custody
+    TransferV1CpiBuilder::new(&ctx.accounts.mpl_core_program)
+        .asset(&ctx.accounts.asset)
+        .collection(ctx.accounts.collection.as_ref())
+        .payer(&ctx.accounts.seller.to_account_info())
+        .authority(Some(&ctx.accounts.seller.to_account_info()))
+        .new_owner(&ctx.accounts.auction.to_account_info())
+        .system_program(Some(&ctx.accounts.system_program.to_account_info()))
+        .invoke()?;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.marketplace = ctx.accounts.marketplace.key();
+    auction.seller = ctx.accounts.seller.key();
+    auction.nft_mint = ctx.accounts.asset.key();
+    auction.starting_price = starting_price;
+    auction.current_bid = 0;
+    auction.reserve_price = reserve_price;
+    auction.buy_now_price = None;
+    auction.highest_bidder = None;
+    auction.start_time = start_time;
+    auction.end_time = start_time + duration;
+    auction.is_active = true;
+    auction.asset_standard = AssetStandard::Core;
+    auction.bump = ctx.bumps.auction;
+
+    msg!("Core auction created with starting price {} lamports", starting_price);
+    Ok(())
+}
+
+pub fn settle_core_auction<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SettleCoreAuction<'info>>,
+) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    let clock = Clock::get()?;
+
+    require!(auction.is_active, MarketplaceError::AuctionNotActive);
+    require!(clock.unix_timestamp >= auction.end_time, MarketplaceError::AuctionNotEnded);
+    require!(
+        auction.asset_standard == AssetStandard::Core,
+        MarketplaceError::InvalidTokenStandard
+    );
+
+    let marketplace_key = ctx.accounts.marketplace.key();
+    let asset_key = ctx.accounts.asset.key();
+    let seeds = &[
+        b"auction",
+        marketplace_key.as_ref(),
+        asset_key.as_ref(),
+        &[auction.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let auction_info = ctx.accounts.auction.to_account_info();
+
+    if auction.current_bid >= auction.reserve_price && auction.highest_bidder.is_some() {
+        let price = auction.current_bid;
+        let marketplace_fee = calculate_fee(price, ctx.accounts.marketplace.fee_basis_points)?;
+
+        let royalties = fetch_core_royalties(&ctx.accounts.asset, ctx.accounts.collection.as_ref());
+        let payouts = core_creator_payouts(royalties.as_ref(), price, ctx.remaining_accounts)?;
+
+        let mut royalty_paid: u64 = 0;
+        for (creator, amount) in payouts {
+            pay_from_escrow(&auction_info, creator, amount)?;
+            royalty_paid += amount;
+        }
+
+        let keeper_amount = keeper_payout(
+            price,
+            ctx.accounts.marketplace.keeper_tip_basis_points,
+            0,
+            &[marketplace_fee, royalty_paid],
+        )?;
+        let seller_amount = seller_proceeds(price, &[marketplace_fee, royalty_paid, keeper_amount])?;
+
+        // Transfer fees and payment
+        pay_from_escrow(&auction_info, &ctx.accounts.treasury, marketplace_fee)?;
+
+        pay_from_escrow(&auction_info, &ctx.accounts.keeper.to_account_info(), keeper_amount)?;
+
+        pay_from_escrow(&auction_info, &ctx.accounts.seller.to_account_info(), seller_amount)?;
+
+        // Transfer asset to winner
+        TransferV1CpiBuilder::new(&ctx.accounts.mpl_core_program)
+            .asset(&ctx.accounts.asset)
+            .collection(ctx.accounts.collection.as_ref())
+            .payer(&ctx.accounts.keeper.to_account_info())
+            .authority(Some(&auction_info))
+            .new_owner(&ctx.accounts.winner)
+            .system_program(Some(&ctx.accounts.system_program.to_account_info()))
+            .invoke_signed(signer)?;
+
+        let marketplace = &mut ctx.accounts.marketplace;
+        marketplace.total_sales += 1;
+        marketplace.total_volume += price;
+
+        msg!("Core auction settled - asset sold for {} lamports", price);
+    } else {
+        // Return asset to seller if reserve not met
+        TransferV1CpiBuilder::new(&ctx.accounts.mpl_core_program)
+            .asset(&ctx.accounts.asset)
+            .collection(ctx.accounts.collection.as_ref())
+            .payer(&ctx.accounts.keeper.to_account_info())
+            .authority(Some(&auction_info))
+            .new_owner(&ctx.accounts.seller.to_account_info())
+            .system_program(Some(&ctx.accounts.system_program.to_account_info()))
+            .invoke_signed(signer)?;
+
+        // Refund highest bidder
+        if auction.highest_bidder.is_some() {
+            pay_from_escrow(&auction_info, &ctx.accounts.winner, auction.current_bid)?;
+        }
+
+        msg!("Core auction ended - reserve price not met");
+    }
+
+    Ok(())
+}
+
+/// Returns a listed Core asset to its seller and closes the listing.
+pub fn cancel_core_listing(ctx: Context<CancelCoreListing>) -> Result<()> {
+    let listing = &ctx.accounts.listing;
+    require!(
+        listing.asset_standard == AssetStandard::Core,
+        MarketplaceError::InvalidTokenStandard
+    );
+
+    let seeds = &[
+        b"listing",
+        listing.marketplace.as_ref(),
+        listing.nft_mint.as_ref(),
+        &[listing.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    TransferV1CpiBuilder::new(&ctx.accounts.mpl_core_program)
+        .asset(&ctx.accounts.asset)
+        .collection(ctx.accounts.collection.as_ref())
+        .payer(&ctx.accounts.seller.to_account_info())
+        .authority(Some(&ctx.accounts.listing.to_account_info()))
+        .new_owner(&ctx.accounts.seller.to_account_info())
+        .system_program(Some(&ctx.accounts.system_program.to_account_info()))
+        .invoke_signed(signer)?;
+
+    msg!("Core listing cancelled");
+    Ok(())
+}
+
+/// Returns a Core asset to its seller from an auction nobody has bid on.
+pub fn cancel_core_auction(ctx: Context<CancelCoreAuction>) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    require!(auction.highest_bidder.is_none(), MarketplaceError::AuctionHasBids);
+    require!(
+        auction.asset_standard == AssetStandard::Core,
+        MarketplaceError::InvalidTokenStandard
+    );
+
+    let seeds = &[
+        b"auction",
+        auction.marketplace.as_ref(),
+        auction.nft_mint.as_ref(),
+        &[auction.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    TransferV1CpiBuilder::new(&ctx.accounts.mpl_core_program)
+        .asset(&ctx.accounts.asset)
+        .collection(ctx.accounts.collection.as_ref())
+        .payer(&ctx.accounts.seller.to_account_info())
+        .authority(Some(&ctx.accounts.auction.to_account_info()))
+        .new_owner(&ctx.accounts.seller.to_account_info())
+        .system_program(Some(&ctx.accounts.system_program.to_account_info()))
+        .invoke_signed(signer)?;
+
+    msg!("Core auction cancelled");
+    Ok(())
+}
+
+/// Reads the `Royalties` plugin from the asset, falling back to the
+/// collection the asset belongs to.
+fn fetch_core_royalties(
+    asset: &AccountInfo,
+    collection: Option<&AccountInfo>,
+) -> Option<Royalties> {
+    if let Ok((_, royalties, _)) = fetch_plugin::<BaseAssetV1, Royalties>(asset, PluginType::Royalties) {
+        return Some(royalties);
+    }
+    collection.and_then(|collection| {
+        fetch_plugin::<BaseCollectionV1, Royalties>(collection, PluginType::Royalties)
+            .ok()
+            .map(|(_, royalties, _)| royalties)
+    })
+}
+
+/// Core checks the `Royalties` rule set on every transfer against the
+/// program behind the transfer authority, which for a sale is this
+/// program's escrow PDA. An asset this program may not move could never
+/// leave escrow, so it is refused before it is taken in.
+fn check_royalty_rule_set(royalties: Option<&Royalties>) -> Result<()> {
+    let allowed = match royalties.map(|royalties| &royalties.rule_set) {
+        None | Some(RuleSet::None) => true,
+        Some(RuleSet::ProgramAllowList(programs)) => programs.contains(&crate::ID),
+        Some(RuleSet::ProgramDenyList(programs)) => !programs.contains(&crate::ID),
+    };
+    require!(allowed, MarketplaceError::RoyaltyRuleSetViolation);
+    Ok(())
+}
+
+/// Splits the royalty on `price` between the plugin creators. Creator
+/// accounts must be passed in the same order as the plugin lists them.
+/// Rounding dust from the percentage split stays with the seller.
+fn core_creator_payouts<'a, 'info>(
+    royalties: Option<&Royalties>,
+    price: u64,
+    creator_accounts: &'a [AccountInfo<'info>],
+) -> Result<Vec<(&'a AccountInfo<'info>, u64)>> {
+    let royalties = match royalties {
+        Some(royalties) => royalties,
+        None => return Ok(Vec::new()),
+    };
+    require!(
+        creator_accounts.len() >= royalties.creators.len(),
+        MarketplaceError::CreatorMismatch
+    );
+
+    let royalty = calculate_fee(price, royalties.basis_points)?;
+    let mut payouts = Vec::with_capacity(royalties.creators.len());
+    for (creator, account) in royalties.creators.iter().zip(creator_accounts.iter()) {
+        require_keys_eq!(account.key(), creator.address, MarketplaceError::CreatorMismatch);
+        let amount = percentage_share(royalty, creator.percentage)?;
+        payouts.push((account, amount));
+    }
+    Ok(payouts)
+}
+
+#[derive(Accounts)]
+pub struct ListCoreAsset<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + Listing::LEN,
+        seeds = [b"listing", marketplace.key().as_ref(), asset.key().as_ref()],
+        bump
+    )]
+    pub listing: Account<'info, Listing>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    /// CHECK: Metaplex Core asset, deserialized and checked in the handler
+    #[account(mut, owner = mpl_core::ID)]
+    pub asset: AccountInfo<'info>,
+
+    /// CHECK: Optional Core collection the asset belongs to
+    pub collection: Option<AccountInfo<'info>>,
+
+    /// CHECK: Metaplex Core program
+    #[account(address = mpl_core::ID)]
+    pub mpl_core_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyCoreAsset<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"listing", marketplace.key().as_ref(), asset.key().as_ref()],
+        bump = listing.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = listing.is_active,
+        close = seller
+    )]
+    pub listing: Account<'info, Listing>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mut, constraint = seller.key() == listing.seller)]
+    pub seller: SystemAccount<'info>,
+
+    /// CHECK: Metaplex Core asset owned by the listing PDA
+    #[account(mut, owner = mpl_core::ID)]
+    pub asset: AccountInfo<'info>,
+
+    /// CHECK: Optional Core collection the asset belongs to
+    pub collection: Option<AccountInfo<'info>>,
+
+    /// CHECK: Treasury account
+    #[account(mut, constraint = treasury.key() == marketplace.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK: Metaplex Core program
+    #[account(address = mpl_core::ID)]
+    pub mpl_core_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateCoreAuction<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + Auction::LEN,
+        seeds = [b"auction", marketplace.key().as_ref(), asset.key().as_ref()],
+        bump
+    )]
+    pub auction: Account<'info, Auction>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    /// CHECK: Metaplex Core asset, deserialized and checked in the handler
+    #[account(mut, owner = mpl_core::ID)]
+    pub asset: AccountInfo<'info>,
+
+    /// CHECK: Optional Core collection the asset belongs to
+    pub collection: Option<AccountInfo<'info>>,
+
+    /// CHECK: Metaplex Core program
+    #[account(address = mpl_core::ID)]
+    pub mpl_core_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleCoreAuction<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", marketplace.key().as_ref(), asset.key().as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        close = seller
+    )]
+    pub auction: Account<'info, Auction>,
+
+    #[account(mut, constraint = seller.key() == auction.seller)]
+    pub seller: SystemAccount<'info>,
+
+    /// CHECK: Winner (highest bidder), checked against the auction
+    #[account(
+        mut,
+        constraint = auction.highest_bidder.is_none_or(|bidder| bidder == winner.key())
+            @ MarketplaceError::InvalidWinner
+    )]
+    pub winner: AccountInfo<'info>,
+
+    /// CHECK: Metaplex Core asset owned by the auction PDA
+    #[account(mut, owner = mpl_core::ID)]
+    pub asset: AccountInfo<'info>,
+
+    /// CHECK: Optional Core collection the asset belongs to
+    pub collection: Option<AccountInfo<'info>>,
+
+    /// Whoever runs settlement; paid the keeper tip on a sale
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    /// CHECK: Treasury account
+    #[account(mut, constraint = treasury.key() == marketplace.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK: Metaplex Core program
+    #[account(address = mpl_core::ID)]
+    pub mpl_core_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelCoreListing<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"listing", marketplace.key().as_ref(), asset.key().as_ref()],
+        bump = listing.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = listing.seller == seller.key(),
+        constraint = listing.is_active,
+        close = seller
+    )]
+    pub listing: Account<'info, Listing>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    /// CHECK: Metaplex Core asset owned by the listing PDA
+    #[account(mut, owner = mpl_core::ID)]
+    pub asset: AccountInfo<'info>,
+
+    /// CHECK: Optional Core collection the asset belongs to
+    pub collection: Option<AccountInfo<'info>>,
+
+    /// CHECK: Metaplex Core program
+    #[account(address = mpl_core::ID)]
+    pub mpl_core_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelCoreAuction<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", marketplace.key().as_ref(), asset.key().as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = auction.seller == seller.key(),
+        constraint = auction.is_active,
+        close = seller
+    )]
+    pub auction: Account<'info, Auction>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    /// CHECK: Metaplex Core asset owned by the auction PDA
+    #[account(mut, owner = mpl_core::ID)]
+    pub asset: AccountInfo<'info>,
+
+    /// CHECK: Optional Core collection the asset belongs to
+    pub collection: Option<AccountInfo<'info>>,
+
+    /// CHECK: Metaplex Core program
+    #[account(address = mpl_core::ID)]
+    pub mpl_core_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn royalties(rule_set: RuleSet) -> Royalties {
+        Royalties {
+            basis_points: 500,
+            creators: Vec::new(),
+            rule_set,
+        }
+    }
+
+    #[test]
+    fn rule_set_none_is_allowed() {
+        assert!(check_royalty_rule_set(None).is_ok());
+        assert!(check_royalty_rule_set(Some(&royalties(RuleSet::None))).is_ok());
+    }
+
+    #[test]
+    fn allow_list_must_name_this_program() {
+        let listed = royalties(RuleSet::ProgramAllowList(vec![crate::ID]));
+        let unlisted = royalties(RuleSet::ProgramAllowList(vec![Pubkey::new_unique()]));
+        assert!(check_royalty_rule_set(Some(&listed)).is_ok());
+        assert_eq!(
+            check_royalty_rule_set(Some(&unlisted)).unwrap_err(),
+            MarketplaceError::RoyaltyRuleSetViolation.into()
+        );
+    }
+
+    #[test]
+    fn deny_list_must_not_name_this_program() {
+        let denied = royalties(RuleSet::ProgramDenyList(vec![crate::ID]));
+        let other = royalties(RuleSet::ProgramDenyList(vec![Pubkey::new_unique()]));
+        assert!(check_royalty_rule_set(Some(&other)).is_ok());
+        assert_eq!(
+            check_royalty_rule_set(Some(&denied)).unwrap_err(),
+            MarketplaceError::RoyaltyRuleSetViolation.into()
+        );
+    }
+}
@@ -0,0 +1,587 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
+use anchor_spl::token::{self, CloseAccount, Token, TokenAccount, Transfer};
+use crate::{calculate_fee, seller_proceeds, Marketplace, MarketplaceError};
+
+pub const MAX_DEAL_ASSETS: usize = 5;
+
+/// Accounts per asset passed to `deposit_deal_leg`: mint, source token
+/// account, escrow token account.
+const DEPOSIT_ACCOUNTS_PER_ASSET: usize = 3;
+
+/// Accounts per asset passed to `withdraw_deal_leg`: escrow token account,
+/// return token account.
+const WITHDRAW_ACCOUNTS_PER_ASSET: usize = 2;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct DealAsset {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+impl DealAsset {
+    pub const LEN: usize = 32 + 8;
+}
+
+/// OTC settlement between two parties. Each side deposits its leg into
+/// escrow token accounts owned by this PDA; once both legs are funded either
+/// party can execute, and before that either party can withdraw after
+/// `expires_at`.
+#[account]
+pub struct Deal {
+    pub marketplace: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub deal_id: u64,
+    pub maker_assets: Vec<DealAsset>,
+    pub taker_assets: Vec<DealAsset>,
+    pub maker_funded: bool,
+    pub taker_funded: bool,
+    pub expires_at: i64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Deal {
+    pub const LEN: usize = 32
+        + 32
+        + 32
+        + 8
+        + (4 + DealAsset::LEN * MAX_DEAL_ASSETS)
+        + (4 + DealAsset::LEN * MAX_DEAL_ASSETS)
+        + 1
+        + 1
+        + 8
+        + 8
+        + 1;
+}
+
+pub fn create_deal(
+    ctx: Context<CreateDeal>,
+    deal_id: u64,
+    taker: Pubkey,
+    maker_assets: Vec<DealAsset>,
+    taker_assets: Vec<DealAsset>,
+    expires_at: i64,
+) -> Result<()> {
+    validate_deal_assets(&maker_assets, &taker_assets)?;
+    require_keys_neq!(taker, ctx.accounts.maker.key(), MarketplaceError::InvalidDealTerms);
+
+    let clock = Clock::get()?;
+    require!(expires_at > clock.unix_timestamp, MarketplaceError::InvalidDuration);
+
+    let deal = &mut ctx.accounts.deal;
+    deal.marketplace = ctx.accounts.marketplace.key();
+    deal.maker = ctx.accounts.maker.key();
+    deal.taker = taker;
+    deal.deal_id = deal_id;
+    deal.maker_assets = maker_assets;
+    deal.taker_assets = taker_assets;
+    deal.maker_funded = false;
+    deal.taker_funded = false;
+    deal.expires_at = expires_at;
+    deal.created_at = clock.unix_timestamp;
+    deal.bump = ctx.bumps.deal;
+
+    msg!("Deal {} created between {} and {}", deal_id, deal.maker, taker);
+    Ok(())
+}
+
+/// Checks both legs of a deal. Every mint may appear only once across the
+/// two legs: each asset is escrowed in the deal's ATA for its mint, so a
+/// repeated mint would share one escrow and could never be closed.
+fn validate_deal_assets(maker_assets: &[DealAsset], taker_assets: &[DealAsset]) -> Result<()> {
+    require!(
+        !maker_assets.is_empty() && maker_assets.len() <= MAX_DEAL_ASSETS,
+        MarketplaceError::InvalidDealTerms
+    );
+    require!(
+        !taker_assets.is_empty() && taker_assets.len() <= MAX_DEAL_ASSETS,
+        MarketplaceError::InvalidDealTerms
+    );
+
+    let assets: Vec<&DealAsset> = maker_assets.iter().chain(taker_assets.iter()).collect();
+    require!(
+        assets.iter().all(|asset| asset.amount > 0),
+        MarketplaceError::InvalidDealTerms
+    );
+    for (index, asset) in assets.iter().enumerate() {
+        require!(
+            assets[index + 1..].iter().all(|other| other.mint != asset.mint),
+            MarketplaceError::InvalidDealTerms
+        );
+    }
+    Ok(())
+}
+
+pub fn deposit_deal_leg<'info>(
+    ctx: Context<'_, '_, 'info, 'info, DepositDealLeg<'info>>,
+) -> Result<()> {
+    let deal = &ctx.accounts.deal;
+    let party = ctx.accounts.party.key();
+    let is_maker = party == deal.maker;
+    require!(is_maker || party == deal.taker, MarketplaceError::UnauthorizedDealParty);
+
+    let (assets, funded) = if is_maker {
+        (deal.maker_assets.clone(), deal.maker_funded)
+    } else {
+        (deal.taker_assets.clone(), deal.taker_funded)
+    };
+    require!(!funded, MarketplaceError::DealLegAlreadyFunded);
+
+    let remaining = ctx.remaining_accounts;
+    require!(
+        remaining.len() == assets.len() * DEPOSIT_ACCOUNTS_PER_ASSET,
+        MarketplaceError::InvalidDealAccounts
+    );
+
+    let deal_info = ctx.accounts.deal.to_account_info();
+    for (item, asset) in remaining.chunks(DEPOSIT_ACCOUNTS_PER_ASSET).zip(assets.iter()) {
+        let (mint, source_account, escrow_account) = (&item[0], &item[1], &item[2]);
+        require_keys_eq!(mint.key(), asset.mint, MarketplaceError::InvalidDealAccounts);
+
+        // Create the deal-owned escrow; fails unless it is the canonical ATA
+        let cpi_accounts = Create {
+            payer: ctx.accounts.party.to_account_info(),
+            associated_token: escrow_account.clone(),
+            authority: deal_info.clone(),
+            mint: mint.clone(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+        associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+
+        let cpi_accounts = Transfer {
+            from: source_account.clone(),
+            to: escrow_account.clone(),
+            authority: ctx.accounts.party.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, asset.amount)?;
+    }
+
+    let deal = &mut ctx.accounts.deal;
+    if is_maker {
+        deal.maker_funded = true;
+    } else {
+        deal.taker_funded = true;
+    }
+
+    msg!("Deal {} leg funded by {}", deal.deal_id, party);
+    Ok(())
+}
+
+/// Remaining accounts: the maker's assets followed by the taker's assets,
+/// each as mint, escrow token account, recipient token account, and the
+/// treasury token account for that mint when the asset owes a fee.
+pub fn execute_deal<'info>(ctx: Context<'_, '_, 'info, 'info, ExecuteDeal<'info>>) -> Result<()> {
+    let deal = &ctx.accounts.deal;
+    let executor = ctx.accounts.executor.key();
+    require!(
+        executor == deal.maker || executor == deal.taker,
+        MarketplaceError::UnauthorizedDealParty
+    );
+    require!(deal.maker_funded && deal.taker_funded, MarketplaceError::DealNotFunded);
+
+    let fee_basis_points = ctx.accounts.marketplace.fee_basis_points;
+    let treasury = ctx.accounts.marketplace.treasury;
+    let maker_key = deal.maker;
+    let deal_id = deal.deal_id.to_le_bytes();
+    let seeds = &[
+        b"deal",
+        deal.marketplace.as_ref(),
+        maker_key.as_ref(),
+        deal_id.as_ref(),
+        &[deal.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let deal_info = ctx.accounts.deal.to_account_info();
+
+    // Maker's leg goes to the taker and vice versa
+    let legs = [
+        (deal.maker_assets.clone(), ctx.accounts.taker.to_account_info(), ctx.accounts.maker.to_account_info()),
+        (deal.taker_assets.clone(), ctx.accounts.maker.to_account_info(), ctx.accounts.taker.to_account_info()),
+    ];
+
+    let remaining = ctx.remaining_accounts;
+    let mut cursor = 0;
+
+    for (assets, recipient, depositor) in legs.iter() {
+        for asset in assets.iter() {
+            require!(remaining.len() >= cursor + 3, MarketplaceError::InvalidDealAccounts);
+            let mint = &remaining[cursor];
+            let escrow_account = &remaining[cursor + 1];
+            let recipient_account = &remaining[cursor + 2];
+            cursor += 3;
+            require_keys_eq!(mint.key(), asset.mint, MarketplaceError::InvalidDealAccounts);
+
+            let marketplace_fee = calculate_fee(asset.amount, fee_basis_points)?;
+            let recipient_amount = seller_proceeds(asset.amount, &[marketplace_fee])?;
+
+            // Fees are taken in the asset's own mint; whole NFTs round to zero
+            if marketplace_fee > 0 {
+                require!(remaining.len() > cursor, MarketplaceError::InvalidDealAccounts);
+                let treasury_account = &remaining[cursor];
+                cursor += 1;
+
+                let treasury_token = Account::<TokenAccount>::try_from(treasury_account)?;
+                require_keys_eq!(treasury_token.owner, treasury, MarketplaceError::InvalidDealAccounts);
+                require_keys_eq!(treasury_token.mint, asset.mint, MarketplaceError::InvalidDealAccounts);
+
+                let cpi_accounts = Transfer {
+                    from: escrow_account.clone(),
+                    to: treasury_account.clone(),
+                    authority: deal_info.clone(),
+                };
+                let cpi_program = ctx.accounts.token_program.to_account_info();
+                let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+                token::transfer(cpi_ctx, marketplace_fee)?;
+            }
+
+            let cpi_accounts = Create {
+                payer: ctx.accounts.executor.to_account_info(),
+                associated_token: recipient_account.clone(),
+                authority: recipient.clone(),
+                mint: mint.clone(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+            associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+
+            let cpi_accounts = Transfer {
+                from: escrow_account.clone(),
+                to: recipient_account.clone(),
+                authority: deal_info.clone(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, recipient_amount)?;
+
+            close_escrow(&ctx.accounts.token_program, escrow_account, depositor, &deal_info, signer)?;
+        }
+    }
+
+    let marketplace = &mut ctx.accounts.marketplace;
+    marketplace.total_sales += 1;
+
+    msg!("Deal {} executed", u64::from_le_bytes(deal_id));
+    Ok(())
+}
+
+/// Remaining accounts, per asset of the signer's leg: escrow token account,
+/// return token account.
+pub fn withdraw_deal_leg<'info>(
+    ctx: Context<'_, '_, 'info, 'info, WithdrawDealLeg<'info>>,
+) -> Result<()> {
+    let deal = &ctx.accounts.deal;
+    let party = ctx.accounts.party.key();
+    let is_maker = party == deal.maker;
+    require!(is_maker || party == deal.taker, MarketplaceError::UnauthorizedDealParty);
+    require!(
+        Clock::get()?.unix_timestamp >= deal.expires_at,
+        MarketplaceError::DealNotExpired
+    );
+
+    let (assets, funded) = if is_maker {
+        (deal.maker_assets.clone(), deal.maker_funded)
+    } else {
+        (deal.taker_assets.clone(), deal.taker_funded)
+    };
+    require!(funded, MarketplaceError::DealNotFunded);
+
+    let remaining = ctx.remaining_accounts;
+    require!(
+        remaining.len() == assets.len() * WITHDRAW_ACCOUNTS_PER_ASSET,
+        MarketplaceError::InvalidDealAccounts
+    );
+
+    let maker_key = deal.maker;
+    let deal_id = deal.deal_id.to_le_bytes();
+    let seeds = &[
+        b"deal",
+        deal.marketplace.as_ref(),
+        maker_key.as_ref(),
+        deal_id.as_ref(),
+        &[deal.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let deal_info = ctx.accounts.deal.to_account_info();
+    let party_info = ctx.accounts.party.to_account_info();
+
+    for (item, asset) in remaining.chunks(WITHDRAW_ACCOUNTS_PER_ASSET).zip(assets.iter()) {
+        let (escrow_account, return_account) = (&item[0], &item[1]);
+
+        let escrow_token = Account::<TokenAccount>::try_from(escrow_account)?;
+        require_keys_eq!(escrow_token.mint, asset.mint, MarketplaceError::InvalidDealAccounts);
+        let return_token = Account::<TokenAccount>::try_from(return_account)?;
+        require_keys_eq!(return_token.owner, party, MarketplaceError::InvalidDealAccounts);
+
+        let cpi_accounts = Transfer {
+            from: escrow_account.clone(),
+            to: return_account.clone(),
+            authority: deal_info.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, asset.amount)?;
+
+        close_escrow(&ctx.accounts.token_program, escrow_account, &party_info, &deal_info, signer)?;
+    }
+
+    let deal = &mut ctx.accounts.deal;
+    if is_maker {
+        deal.maker_funded = false;
+    } else {
+        deal.taker_funded = false;
+    }
+
+    // Nothing left in escrow, so the deal account can go back to the maker
+    if !deal.maker_funded && !deal.taker_funded {
+        ctx.accounts.deal.close(ctx.accounts.maker.to_account_info())?;
+    }
+
+    msg!("Deal {} leg withdrawn by {}", u64::from_le_bytes(deal_id), party);
+    Ok(())
+}
+
+/// Drops a deal that neither party has funded yet. Either party may cancel;
+/// the account rent goes back to the maker.
+pub fn cancel_deal(ctx: Context<CancelDeal>) -> Result<()> {
+    let deal = &ctx.accounts.deal;
+    let party = ctx.accounts.party.key();
+    require!(
+        party == deal.maker || party == deal.taker,
+        MarketplaceError::UnauthorizedDealParty
+    );
+    require!(
+        !deal.maker_funded && !deal.taker_funded,
+        MarketplaceError::DealLegAlreadyFunded
+    );
+
+    msg!("Deal {} cancelled by {}", deal.deal_id, party);
+    Ok(())
+}
+
+fn close_escrow<'info>(
+    token_program: &Program<'info, Token>,
+    escrow_account: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    deal_info: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    let cpi_accounts = CloseAccount {
+        account: escrow_account.clone(),
+        destination: destination.clone(),
+        authority: deal_info.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token::close_account(cpi_ctx)
+}
+
+#[derive(Accounts)]
+#[instruction(deal_id: u64)]
+pub struct CreateDeal<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = 8 + Deal::LEN,
+        seeds = [
+            b"deal",
+            marketplace.key().as_ref(),
+            maker.key().as_ref(),
+            deal_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub deal: Account<'info, Deal>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositDealLeg<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"deal",
+            marketplace.key().as_ref(),
+            deal.maker.as_ref(),
+            deal.deal_id.to_le_bytes().as_ref()
+        ],
+        bump = deal.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub deal: Account<'info, Deal>,
+
+    #[account(mut)]
+    pub party: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteDeal<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"deal",
+            marketplace.key().as_ref(),
+            deal.maker.as_ref(),
+            deal.deal_id.to_le_bytes().as_ref()
+        ],
+        bump = deal.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        close = maker
+    )]
+    pub deal: Account<'info, Deal>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    #[account(mut, constraint = maker.key() == deal.maker)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(mut, constraint = taker.key() == deal.taker)]
+    pub taker: SystemAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawDealLeg<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"deal",
+            marketplace.key().as_ref(),
+            deal.maker.as_ref(),
+            deal.deal_id.to_le_bytes().as_ref()
+        ],
+        bump = deal.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub deal: Account<'info, Deal>,
+
+    #[account(mut)]
+    pub party: Signer<'info>,
+
+    #[account(mut, constraint = maker.key() == deal.maker)]
+    pub maker: SystemAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelDeal<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"deal",
+            marketplace.key().as_ref(),
+            deal.maker.as_ref(),
+            deal.deal_id.to_le_bytes().as_ref()
+        ],
+        bump = deal.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        close = maker
+    )]
+    pub deal: Account<'info, Deal>,
+
+    pub party: Signer<'info>,
+
+    #[account(mut, constraint = maker.key() == deal.maker)]
+    pub maker: SystemAccount<'info>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(mint: Pubkey, amount: u64) -> DealAsset {
+        DealAsset { mint, amount }
+    }
+
+    #[test]
+    fn accepts_distinct_mints() {
+        let maker = [asset(Pubkey::new_unique(), 1), asset(Pubkey::new_unique(), 1)];
+        let taker = [asset(Pubkey::new_unique(), 500)];
+        assert!(validate_deal_assets(&maker, &taker).is_ok());
+    }
+
+    #[test]
+    fn rejects_duplicate_mint_within_a_leg() {
+        let mint = Pubkey::new_unique();
+        let maker = [asset(mint, 1), asset(mint, 1)];
+        let taker = [asset(Pubkey::new_unique(), 500)];
+        assert_eq!(
+            validate_deal_assets(&maker, &taker).unwrap_err(),
+            MarketplaceError::InvalidDealTerms.into()
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_mint_across_legs() {
+        let mint = Pubkey::new_unique();
+        let maker = [asset(mint, 1)];
+        let taker = [asset(Pubkey::new_unique(), 500), asset(mint, 10)];
+        assert_eq!(
+            validate_deal_assets(&maker, &taker).unwrap_err(),
+            MarketplaceError::InvalidDealTerms.into()
+        );
+    }
+
+    #[test]
+    fn rejects_empty_oversized_or_zero_legs() {
+        let one = [asset(Pubkey::new_unique(), 1)];
+        let oversized: Vec<DealAsset> = (0..=MAX_DEAL_ASSETS)
+            .map(|_| asset(Pubkey::new_unique(), 1))
+            .collect();
+        let zero = [asset(Pubkey::new_unique(), 0)];
+
+        assert!(validate_deal_assets(&[], &one).is_err());
+        assert!(validate_deal_assets(&one, &[]).is_err());
+        assert!(validate_deal_assets(&oversized, &one).is_err());
+        assert!(validate_deal_assets(&one, &zero).is_err());
+    }
+}
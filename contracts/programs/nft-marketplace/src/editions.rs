@@ -0,0 +1,504 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, CloseAccount, Mint, MintTo, Token, TokenAccount, Transfer};
+use mpl_token_metadata::{
+    accounts::MasterEdition,
+    instructions::{
+        MintNewEditionFromMasterEditionViaTokenCpi,
+        MintNewEditionFromMasterEditionViaTokenCpiAccounts,
+        MintNewEditionFromMasterEditionViaTokenInstructionArgs,
+    },
+    types::{Key as MetadataKey, MintNewEditionFromMasterEditionViaTokenArgs},
+};
+use crate::metaplex_standards::metadata_creator_payouts;
+use crate::{calculate_fee, pay_from_buyer, seller_proceeds, Marketplace, MarketplaceError};
+
+/// Open or limited edition sale backed by an escrowed master edition token.
+/// Every purchase prints a new edition directly to the buyer and pays the
+/// master's metadata creators their royalty.
+#[account]
+pub struct EditionListing {
+    pub marketplace: Pubkey,
+    pub seller: Pubkey,
+    pub master_mint: Pubkey,
+    pub price: u64,
+    pub max_prints: u64,
+    pub prints_sold: u64,
+    pub is_active: bool,
+    pub listed_at: i64,
+    pub bump: u8,
+}
+
+impl EditionListing {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8 + 1 + 8 + 1;
+}
+
+/// Reads the master edition of an escrowed master token. Token Metadata
+/// owns the supply counter, so it is the source of truth for print numbers.
+fn read_master_edition(master_edition: &AccountInfo) -> Result<MasterEdition> {
+    require_keys_eq!(*master_edition.owner, mpl_token_metadata::ID, MarketplaceError::InvalidMetadata);
+    let edition =
+        MasterEdition::try_from(master_edition).map_err(|_| MarketplaceError::InvalidMetadata)?;
+    require!(edition.key == MetadataKey::MasterEditionV2, MarketplaceError::InvalidMetadata);
+    Ok(edition)
+}
+
+/// Prints the master can still produce, `None` if its supply is unlimited.
+pub fn remaining_master_prints(edition: &MasterEdition) -> Option<u64> {
+    edition.max_supply.map(|max_supply| max_supply.saturating_sub(edition.supply))
+}
+
+pub fn sell_edition(
+    ctx: Context<SellEdition>,
+    price: u64,
+    max_prints: u64,
+) -> Result<()> {
+    require!(price > 0, MarketplaceError::InvalidPrice);
+    require!(max_prints > 0, MarketplaceError::InvalidPrintCap);
+
+    // A cap the master cannot honour would sell prints Token Metadata refuses
+    let master_edition = read_master_edition(&ctx.accounts.master_edition)?;
+    require!(
+        remaining_master_prints(&master_edition).is_none_or(|remaining| max_prints <= remaining),
+        MarketplaceError::InvalidPrintCap
+    );
+
+    let edition_listing = &mut ctx.accounts.edition_listing;
+    edition_listing.marketplace = ctx.accounts.marketplace.key();
+    edition_listing.seller = ctx.accounts.seller.key();
+    edition_listing.master_mint = ctx.accounts.master_mint.key();
+    edition_listing.price = price;
+    edition_listing.max_prints = max_prints;
+    edition_listing.prints_sold = 0;
+    edition_listing.is_active = true;
+    edition_listing.listed_at = Clock::get()?.unix_timestamp;
+    edition_listing.bump = ctx.bumps.edition_listing;
+
+    // Deposit the master edition token into escrow
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.seller_master_account.to_account_info(),
+        to: ctx.accounts.escrow_master_account.to_account_info(),
+        authority: ctx.accounts.seller.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, 1)?;
+
+    msg!("Edition sale opened: {} prints at {} lamports", max_prints, price);
+    Ok(())
+}
+
+/// Remaining accounts are the master's metadata creators in metadata order.
+pub fn buy_edition<'info>(ctx: Context<'_, '_, 'info, 'info, BuyEdition<'info>>) -> Result<()> {
+    let edition_listing = &ctx.accounts.edition_listing;
+    require!(edition_listing.is_active, MarketplaceError::ListingNotActive);
+    require!(
+        edition_listing.prints_sold < edition_listing.max_prints,
+        MarketplaceError::EditionSoldOut
+    );
+
+    let price = edition_listing.price;
+    // Prints made outside this sale also take numbers, so the next one comes
+    // from the master's supply rather than this listing's own counter
+    let edition = read_master_edition(&ctx.accounts.master_edition)?
+        .supply
+        .checked_add(1)
+        .ok_or(MarketplaceError::MathOverflow)?;
+
+    // Calculate fees
+    let marketplace_fee = calculate_fee(price, ctx.accounts.marketplace.fee_basis_points)?;
+
+    // Prints carry the master's creators, so its metadata sets the royalty
+    let payouts = metadata_creator_payouts(
+        &ctx.accounts.master_metadata,
+        &edition_listing.master_mint,
+        price,
+        ctx.remaining_accounts,
+    )?;
+
+    let mut royalty_paid: u64 = 0;
+    for (creator, amount) in payouts {
+        pay_from_buyer(&ctx.accounts.buyer, creator, &ctx.accounts.system_program, amount)?;
+        royalty_paid += amount;
+    }
+
+    let seller_amount = seller_proceeds(price, &[marketplace_fee, royalty_paid])?;
+
+    pay_from_buyer(
+        &ctx.accounts.buyer,
+        &ctx.accounts.treasury,
+        &ctx.accounts.system_program,
+        marketplace_fee,
+    )?;
+    pay_from_buyer(
+        &ctx.accounts.buyer,
+        &ctx.accounts.seller.to_account_info(),
+        &ctx.accounts.system_program,
+        seller_amount,
+    )?;
+
+    // The print mint needs its single token in place before Token Metadata
+    // turns it into an edition
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.print_mint.to_account_info(),
+        to: ctx.accounts.buyer_print_account.to_account_info(),
+        authority: ctx.accounts.buyer.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::mint_to(cpi_ctx, 1)?;
+
+    let master_mint = edition_listing.master_mint;
+    let seeds = &[
+        b"edition_listing",
+        edition_listing.marketplace.as_ref(),
+        master_mint.as_ref(),
+        &[edition_listing.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let token_metadata_program = ctx.accounts.token_metadata_program.to_account_info();
+    let print_mint = ctx.accounts.print_mint.to_account_info();
+    let buyer = ctx.accounts.buyer.to_account_info();
+    let listing_info = ctx.accounts.edition_listing.to_account_info();
+    let escrow_master_account = ctx.accounts.escrow_master_account.to_account_info();
+    let seller = ctx.accounts.seller.to_account_info();
+    let token_program = ctx.accounts.token_program.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    let rent = ctx.accounts.rent.to_account_info();
+
+    MintNewEditionFromMasterEditionViaTokenCpi::new(
+        &token_metadata_program,
+        MintNewEditionFromMasterEditionViaTokenCpiAccounts {
+            new_metadata: &ctx.accounts.print_metadata,
+            new_edition: &ctx.accounts.print_edition,
+            master_edition: &ctx.accounts.master_edition,
+            new_mint: &print_mint,
+            edition_mark_pda: &ctx.accounts.edition_marker,
+            new_mint_authority: &buyer,
+            payer: &buyer,
+            token_account_owner: &listing_info,
+            token_account: &escrow_master_account,
+            new_metadata_update_authority: &seller,
+            metadata: &ctx.accounts.master_metadata,
+            token_program: &token_program,
+            system_program: &system_program,
+            rent: Some(&rent),
+        },
+        MintNewEditionFromMasterEditionViaTokenInstructionArgs {
+            mint_new_edition_from_master_edition_via_token_args:
+                MintNewEditionFromMasterEditionViaTokenArgs { edition },
+        },
+    )
+    .invoke_signed(signer)?;
+
+    let edition_listing = &mut ctx.accounts.edition_listing;
+    edition_listing.prints_sold += 1;
+    if edition_listing.prints_sold == edition_listing.max_prints {
+        edition_listing.is_active = false;
+    }
+
+    let marketplace = &mut ctx.accounts.marketplace;
+    marketplace.total_sales += 1;
+    marketplace.total_volume += price;
+
+    msg!("Edition #{} sold for {} lamports", edition, price);
+    Ok(())
+}
+
+pub fn cancel_edition_sale(ctx: Context<CancelEditionSale>) -> Result<()> {
+    let edition_listing = &ctx.accounts.edition_listing;
+    let master_mint = edition_listing.master_mint;
+    let seeds = &[
+        b"edition_listing",
+        edition_listing.marketplace.as_ref(),
+        master_mint.as_ref(),
+        &[edition_listing.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Return the master edition token to the seller
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.escrow_master_account.to_account_info(),
+        to: ctx.accounts.seller_master_account.to_account_info(),
+        authority: ctx.accounts.edition_listing.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, 1)?;
+
+    let cpi_accounts = CloseAccount {
+        account: ctx.accounts.escrow_master_account.to_account_info(),
+        destination: ctx.accounts.seller.to_account_info(),
+        authority: ctx.accounts.edition_listing.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::close_account(cpi_ctx)?;
+
+    msg!(
+        "Edition sale closed after {} of {} prints",
+        edition_listing.prints_sold,
+        edition_listing.max_prints
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SellEdition<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + EditionListing::LEN,
+        seeds = [b"edition_listing", marketplace.key().as_ref(), master_mint.key().as_ref()],
+        bump
+    )]
+    pub edition_listing: Account<'info, EditionListing>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub master_mint: Account<'info, Mint>,
+
+    /// CHECK: Master edition PDA of the master mint, read in the handler
+    #[account(
+        seeds = [b"metadata", mpl_token_metadata::ID.as_ref(), master_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = mpl_token_metadata::ID
+    )]
+    pub master_edition: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = seller_master_account.mint == master_mint.key(),
+        constraint = seller_master_account.owner == seller.key(),
+        constraint = seller_master_account.amount == 1
+    )]
+    pub seller_master_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = master_mint,
+        associated_token::authority = edition_listing
+    )]
+    pub escrow_master_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyEdition<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"edition_listing",
+            marketplace.key().as_ref(),
+            edition_listing.master_mint.as_ref()
+        ],
+        bump = edition_listing.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = edition_listing.is_active
+    )]
+    pub edition_listing: Account<'info, EditionListing>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mut, constraint = seller.key() == edition_listing.seller)]
+    pub seller: SystemAccount<'info>,
+
+    #[account(
+        constraint = escrow_master_account.mint == edition_listing.master_mint,
+        constraint = escrow_master_account.owner == edition_listing.key(),
+        constraint = escrow_master_account.amount == 1
+    )]
+    pub escrow_master_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Metadata PDA of the master mint; sets the creator royalties
+    /// and is validated again by Token Metadata
+    #[account(
+        seeds = [b"metadata", mpl_token_metadata::ID.as_ref(), edition_listing.master_mint.as_ref()],
+        bump,
+        seeds::program = mpl_token_metadata::ID
+    )]
+    pub master_metadata: AccountInfo<'info>,
+
+    /// CHECK: Master edition PDA of the master mint; its supply numbers the
+    /// print and is updated by Token Metadata
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            mpl_token_metadata::ID.as_ref(),
+            edition_listing.master_mint.as_ref(),
+            b"edition"
+        ],
+        bump,
+        seeds::program = mpl_token_metadata::ID
+    )]
+    pub master_edition: AccountInfo<'info>,
+
+    /// CHECK: Edition marker PDA for the print number, validated by Token Metadata
+    #[account(mut)]
+    pub edition_marker: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = buyer,
+        mint::decimals = 0,
+        mint::authority = buyer,
+        mint::freeze_authority = buyer
+    )]
+    pub print_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = buyer,
+        associated_token::mint = print_mint,
+        associated_token::authority = buyer
+    )]
+    pub buyer_print_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Print metadata PDA, created by Token Metadata
+    #[account(mut)]
+    pub print_metadata: AccountInfo<'info>,
+
+    /// CHECK: Print edition PDA, created by Token Metadata
+    #[account(mut)]
+    pub print_edition: AccountInfo<'info>,
+
+    /// CHECK: Treasury account
+    #[account(mut, constraint = treasury.key() == marketplace.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK: Token Metadata program
+    #[account(address = mpl_token_metadata::ID)]
+    pub token_metadata_program: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct CancelEditionSale<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"edition_listing",
+            marketplace.key().as_ref(),
+            edition_listing.master_mint.as_ref()
+        ],
+        bump = edition_listing.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = edition_listing.seller == seller.key(),
+        close = seller
+    )]
+    pub edition_listing: Account<'info, EditionListing>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_master_account.mint == edition_listing.master_mint,
+        constraint = escrow_master_account.owner == edition_listing.key(),
+        constraint = escrow_master_account.amount == 1
+    )]
+    pub escrow_master_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = seller_master_account.mint == edition_listing.master_mint,
+        constraint = seller_master_account.owner == seller.key()
+    )]
+    pub seller_master_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn master_edition_data(key: MetadataKey, supply: u64, max_supply: Option<u64>) -> Vec<u8> {
+        let mut data = vec![key as u8];
+        data.extend_from_slice(&supply.to_le_bytes());
+        match max_supply {
+            Some(max_supply) => {
+                data.push(1);
+                data.extend_from_slice(&max_supply.to_le_bytes());
+            }
+            None => data.push(0),
+        }
+        // Token Metadata pads master editions past the serialized fields
+        data.resize(282, 0);
+        data
+    }
+
+    fn read(owner: Pubkey, data: &mut [u8]) -> Result<MasterEdition> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, data, &owner, false, 0);
+        read_master_edition(&account)
+    }
+
+    #[test]
+    fn reads_supply_from_master_edition() {
+        let mut data = master_edition_data(MetadataKey::MasterEditionV2, 7, Some(10));
+        let edition = read(mpl_token_metadata::ID, &mut data).unwrap();
+        assert_eq!(edition.supply, 7);
+        assert_eq!(remaining_master_prints(&edition), Some(3));
+    }
+
+    #[test]
+    fn unlimited_master_has_no_print_cap() {
+        let mut data = master_edition_data(MetadataKey::MasterEditionV2, 7, None);
+        let edition = read(mpl_token_metadata::ID, &mut data).unwrap();
+        assert_eq!(remaining_master_prints(&edition), None);
+    }
+
+    #[test]
+    fn exhausted_master_has_no_prints_left() {
+        let mut data = master_edition_data(MetadataKey::MasterEditionV2, 12, Some(10));
+        let edition = read(mpl_token_metadata::ID, &mut data).unwrap();
+        assert_eq!(remaining_master_prints(&edition), Some(0));
+    }
+
+    #[test]
+    fn rejects_accounts_that_are_not_master_editions() {
+        let mut data = master_edition_data(MetadataKey::MasterEditionV2, 0, None);
+        assert_eq!(
+            read(Pubkey::new_unique(), &mut data).unwrap_err(),
+            MarketplaceError::InvalidMetadata.into()
+        );
+        let mut data = master_edition_data(MetadataKey::EditionV1, 0, None);
+        assert_eq!(
+            read(mpl_token_metadata::ID, &mut data).unwrap_err(),
+            MarketplaceError::InvalidMetadata.into()
+        );
+    }
+}
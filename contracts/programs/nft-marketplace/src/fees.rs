@@ -0,0 +1,225 @@
+use anchor_lang::prelude::*;
+use crate::MarketplaceError;
+
+/// 10000 basis points make up the whole price.
+pub const MAX_BASIS_POINTS: u16 = 10000;
+
+/// How a sale price is divided between the treasury, the royalty holder
+/// and the seller.
+///
+/// Rounding policy: the marketplace fee and the royalty are each rounded
+/// down to the smallest unit, so the seller receives the rounding
+/// remainder and the three parts always sum to the price.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SaleSplit {
+    pub marketplace_fee: u64,
+    pub royalty: u64,
+    pub seller_amount: u64,
+}
+
+/// `amount * basis_points / 10000`, rounded down.
+pub fn calculate_fee(amount: u64, basis_points: u16) -> Result<u64> {
+    let fee = (amount as u128)
+        .checked_mul(basis_points as u128)
+        .ok_or(MarketplaceError::MathOverflow)?
+        / MAX_BASIS_POINTS as u128;
+    Ok(u64::try_from(fee).map_err(|_| MarketplaceError::MathOverflow)?)
+}
+
+/// Splits `price` into fee, royalty and seller proceeds. The fee and royalty
+/// rates together may not exceed the whole price.
+pub fn split_sale(price: u64, fee_basis_points: u16, royalty_basis_points: u16) -> Result<SaleSplit> {
+    require!(
+        fee_basis_points as u32 + royalty_basis_points as u32 <= MAX_BASIS_POINTS as u32,
+        MarketplaceError::FeesExceedPrice
+    );
+
+    let marketplace_fee = calculate_fee(price, fee_basis_points)?;
+    let royalty = calculate_fee(price, royalty_basis_points)?;
+    let seller_amount = seller_proceeds(price, &[marketplace_fee, royalty])?;
+
+    Ok(SaleSplit {
+        marketplace_fee,
+        royalty,
+        seller_amount,
+    })
+}
+
+/// What is left of `price` for the seller after `deductions`; fails rather
+/// than paying the seller a negative amount.
+pub fn seller_proceeds(price: u64, deductions: &[u64]) -> Result<u64> {
+    let total = deductions
+        .iter()
+        .try_fold(0u64, |total, deduction| total.checked_add(*deduction))
+        .ok_or(MarketplaceError::MathOverflow)?;
+    Ok(price.checked_sub(total).ok_or(MarketplaceError::FeesExceedPrice)?)
+}
+
+/// What a settlement keeper is paid: the tip on `price` plus the rent it
+/// fronted, capped at what is left after `deductions` so an expensive
+/// keeper can never make a sale unsettleable. The seller absorbs the cap.
+pub fn keeper_payout(
+    price: u64,
+    tip_basis_points: u16,
+    fronted_rent: u64,
+    deductions: &[u64],
+) -> Result<u64> {
+    // Saturating is exact here: anything above `price` is capped anyway
+    let owed = calculate_fee(price, tip_basis_points)?.saturating_add(fronted_rent);
+    Ok(owed.min(seller_proceeds(price, deductions)?))
+}
+
+/// A creator's `percentage` of `amount`, rounded down. Dust left over after
+/// all creators are paid stays with the seller.
+pub fn percentage_share(amount: u64, percentage: u8) -> Result<u64> {
+    let share = (amount as u128)
+        .checked_mul(percentage as u128)
+        .ok_or(MarketplaceError::MathOverflow)?
+        / 100;
+    Ok(u64::try_from(share).map_err(|_| MarketplaceError::MathOverflow)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn calculate_fee_rounds_down() {
+        assert_eq!(calculate_fee(10_000, 250).unwrap(), 250);
+        assert_eq!(calculate_fee(399, 250).unwrap(), 9);
+        assert_eq!(calculate_fee(39, 250).unwrap(), 0);
+        assert_eq!(calculate_fee(u64::MAX, MAX_BASIS_POINTS).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn split_sale_gives_remainder_to_seller() {
+        // 2.5% of 999 is 24.975 and 5% is 49.95; both round down and the
+        // 0.925 of dust goes to the seller.
+        assert_eq!(
+            split_sale(999, 250, 500).unwrap(),
+            SaleSplit {
+                marketplace_fee: 24,
+                royalty: 49,
+                seller_amount: 926,
+            }
+        );
+    }
+
+    #[test]
+    fn split_sale_allows_fee_and_royalty_of_whole_price() {
+        let split = split_sale(1_000, 4_000, 6_000).unwrap();
+        assert_eq!(split.seller_amount, 0);
+    }
+
+    #[test]
+    fn split_sale_rejects_fee_and_royalty_above_whole_price() {
+        assert_eq!(
+            split_sale(1_000, 4_000, 6_001).unwrap_err(),
+            MarketplaceError::FeesExceedPrice.into()
+        );
+        assert_eq!(
+            split_sale(1_000, u16::MAX, u16::MAX).unwrap_err(),
+            MarketplaceError::FeesExceedPrice.into()
+        );
+    }
+
+    #[test]
+    fn seller_proceeds_rejects_deductions_above_price() {
+        assert_eq!(seller_proceeds(100, &[40, 60]).unwrap(), 0);
+        assert_eq!(
+            seller_proceeds(100, &[40, 61]).unwrap_err(),
+            MarketplaceError::FeesExceedPrice.into()
+        );
+        assert_eq!(
+            seller_proceeds(u64::MAX, &[u64::MAX, 1]).unwrap_err(),
+            MarketplaceError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn percentage_share_rounds_down() {
+        assert_eq!(percentage_share(999, 33).unwrap(), 329);
+        assert_eq!(percentage_share(u64::MAX, 100).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn keeper_payout_covers_tip_and_rent() {
+        // 1% tip on 1 SOL plus 2_039_280 of rent, well within proceeds
+        assert_eq!(keeper_payout(1_000_000_000, 100, 2_039_280, &[25_000_000]).unwrap(), 12_039_280);
+    }
+
+    #[test]
+    fn keeper_payout_is_capped_at_remaining_proceeds() {
+        // A small sale cannot cover the ATA rent; the keeper takes the rest
+        // and the seller gets nothing rather than settlement failing.
+        let keeper = keeper_payout(1_000_000, 100, 2_039_280, &[25_000, 50_000]).unwrap();
+        assert_eq!(keeper, 925_000);
+        assert_eq!(seller_proceeds(1_000_000, &[25_000, 50_000, keeper]).unwrap(), 0);
+    }
+
+    proptest! {
+        #[test]
+        fn keeper_payout_always_leaves_sale_settleable(
+            price in any::<u64>(),
+            tip in 0..=MAX_BASIS_POINTS,
+            rent in any::<u64>(),
+            fee in 0..=MAX_BASIS_POINTS,
+            royalty in 0..=MAX_BASIS_POINTS,
+        ) {
+            let royalty = royalty.min(MAX_BASIS_POINTS - fee);
+            let split = split_sale(price, fee, royalty).unwrap();
+            let deductions = [split.marketplace_fee, split.royalty];
+            let keeper = keeper_payout(price, tip, rent, &deductions).unwrap();
+            prop_assert!(keeper <= calculate_fee(price, tip).unwrap().saturating_add(rent));
+            prop_assert!(seller_proceeds(price, &[split.marketplace_fee, split.royalty, keeper]).is_ok());
+        }
+
+        #[test]
+        fn split_sale_parts_sum_to_price(
+            price in any::<u64>(),
+            fee in 0..=MAX_BASIS_POINTS,
+            royalty in 0..=MAX_BASIS_POINTS,
+        ) {
+            let royalty = royalty.min(MAX_BASIS_POINTS - fee);
+            let split = split_sale(price, fee, royalty).unwrap();
+            prop_assert_eq!(
+                split.marketplace_fee as u128 + split.royalty as u128 + split.seller_amount as u128,
+                price as u128
+            );
+            prop_assert_eq!(split.marketplace_fee, calculate_fee(price, fee).unwrap());
+            prop_assert_eq!(split.royalty, calculate_fee(price, royalty).unwrap());
+        }
+
+        #[test]
+        fn split_sale_rejects_rates_above_whole_price(
+            price in any::<u64>(),
+            fee in any::<u16>(),
+            royalty in any::<u16>(),
+        ) {
+            prop_assume!(fee as u32 + royalty as u32 > MAX_BASIS_POINTS as u32);
+            prop_assert_eq!(
+                split_sale(price, fee, royalty).unwrap_err(),
+                MarketplaceError::FeesExceedPrice.into()
+            );
+        }
+
+        #[test]
+        fn calculate_fee_never_exceeds_amount(amount in any::<u64>(), basis_points in 0..=MAX_BASIS_POINTS) {
+            let fee = calculate_fee(amount, basis_points).unwrap();
+            prop_assert!(fee <= amount);
+            // Rounded down: less than one unit short of the exact value.
+            let exact = amount as u128 * basis_points as u128;
+            prop_assert!(fee as u128 * MAX_BASIS_POINTS as u128 <= exact);
+            prop_assert!(exact < (fee as u128 + 1) * MAX_BASIS_POINTS as u128);
+        }
+
+        #[test]
+        fn seller_proceeds_matches_subtraction(price in any::<u64>(), a in any::<u64>(), b in any::<u64>()) {
+            match seller_proceeds(price, &[a, b]) {
+                Ok(rest) => prop_assert_eq!(rest as u128 + a as u128 + b as u128, price as u128),
+                Err(_) => prop_assert!(a as u128 + b as u128 > price as u128),
+            }
+        }
+    }
+}
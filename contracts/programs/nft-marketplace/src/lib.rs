@@ -1,15 +1,40 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use anchor_spl::associated_token::AssociatedToken;
-use mpl_token_metadata::state::{Metadata, TokenMetadataAccount};
+use anchor_lang::system_program;
+use anchor_spl::token::{self, CloseAccount, Token, TokenAccount, Transfer};
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
 
+pub mod admin;
+pub mod batch;
+pub mod bundle;
+pub mod candle_auction;
+pub mod collection;
 pub mod compression;
 pub mod compressed_nft;
+pub mod core_asset;
+pub mod deal;
+pub mod editions;
+pub mod fees;
 pub mod metaplex_standards;
+pub mod multi_unit_auction;
+pub mod swap;
+pub mod sweep;
+pub mod token_auction;
 
+use admin::*;
+use batch::*;
+use bundle::*;
+use candle_auction::*;
+use collection::*;
 use compression::*;
-use compressed_nft::*;
+use core_asset::*;
+use deal::*;
+use editions::*;
+use fees::*;
 use metaplex_standards::*;
+use multi_unit_auction::*;
+use swap::*;
+use sweep::*;
+use token_auction::*;
 
 
 
@@ -20,40 +45,94 @@ declare_id!("42t5wRBjbmH44vgNCxuL9Mpyqg3qDWReDz3UnsGyG6VG");
 pub mod nft_marketplace {
     use super::*;
 
+    /// Creates a storefront owned by the signing creator. `name` tells apart
+    /// the creator's storefronts and is part of the marketplace address.
     pub fn initialize_marketplace(
         ctx: Context<InitializeMarketplace>,
+        name: String,
         fee_basis_points: u16,
+        keeper_tip_basis_points: u16,
     ) -> Result<()> {
+        require!(
+            !name.is_empty() && name.len() <= MAX_MARKETPLACE_NAME_LEN,
+            MarketplaceError::InvalidMarketplaceName
+        );
         require!(fee_basis_points <= 10000, MarketplaceError::InvalidFee);
+        require!(
+            fee_basis_points as u32 + keeper_tip_basis_points as u32 <= 10000,
+            MarketplaceError::InvalidFee
+        );
         
         let marketplace = &mut ctx.accounts.marketplace;
+        marketplace.creator = ctx.accounts.authority.key();
+        marketplace.name = name;
         marketplace.authority = ctx.accounts.authority.key();
+        marketplace.pending_authority = None;
         marketplace.fee_basis_points = fee_basis_points;
+        marketplace.keeper_tip_basis_points = keeper_tip_basis_points;
         marketplace.treasury = ctx.accounts.treasury.key();
         marketplace.total_sales = 0;
         marketplace.total_volume = 0;
         marketplace.bump = ctx.bumps.marketplace;
         
-        msg!("Marketplace initialized with {}% fee", fee_basis_points as f64 / 100.0);
+        msg!(
+            "Marketplace {} initialized with {}% fee",
+            marketplace.name,
+            fee_basis_points as f64 / 100.0
+        );
         Ok(())
     }
 
+    pub fn update_marketplace_config(
+        ctx: Context<UpdateMarketplaceConfig>,
+        fee_basis_points: Option<u16>,
+        keeper_tip_basis_points: Option<u16>,
+        treasury: Option<Pubkey>,
+    ) -> Result<()> {
+        admin::update_marketplace_config(ctx, fee_basis_points, keeper_tip_basis_points, treasury)
+    }
+
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        admin::propose_authority(ctx, new_authority)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        admin::accept_authority(ctx)
+    }
+
     pub fn list_nft(
         ctx: Context<ListNFT>,
         price: u64,
+        quantity: u64,
         royalty_percentage: u16,
+        allowed_buyer: Option<Pubkey>,
+        starts_at: Option<i64>,
     ) -> Result<()> {
         require!(price > 0, MarketplaceError::InvalidPrice);
+        require!(quantity > 0, MarketplaceError::InvalidQuantity);
+        require!(
+            ctx.accounts.seller_nft_account.amount >= quantity,
+            MarketplaceError::InvalidQuantity
+        );
         require!(royalty_percentage <= 5000, MarketplaceError::InvalidRoyalty);
 
+        let now = Clock::get()?.unix_timestamp;
+        let starts_at = resolve_start_time(starts_at, now)?;
+
         let listing = &mut ctx.accounts.listing;
+        listing.marketplace = ctx.accounts.marketplace.key();
         listing.seller = ctx.accounts.seller.key();
         listing.nft_mint = ctx.accounts.nft_mint.key();
         listing.price = price;
+        listing.quantity = quantity;
         listing.royalty_percentage = royalty_percentage;
         listing.royalty_recipient = ctx.accounts.seller.key();
+        listing.allowed_buyer = allowed_buyer;
         listing.is_active = true;
-        listing.listed_at = Clock::get()?.unix_timestamp;
+        listing.listed_at = now;
+        listing.starts_at = starts_at;
+        listing.asset_standard = AssetStandard::Legacy;
+        listing.version = 0;
         listing.bump = ctx.bumps.listing;
 
         // Transfer NFT to escrow
@@ -64,58 +143,102 @@ pub mod nft_marketplace {
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, 1)?;
+        token::transfer(cpi_ctx, quantity)?;
 
-        msg!("NFT listed for {} lamports", price);
+        msg!("{} unit(s) listed for {} lamports each, live from {}", quantity, price, starts_at);
         Ok(())
     }
 
-    }
-
-    pub fn buy_nft(ctx: Context<BuyNFT>) -> Result<()> {
-        let listing = &mut ctx.accounts.listing;
+    /// `expected_price` is the per-unit price the buyer saw; the purchase
+    /// fails if the listing now asks more, or if it was modified since
+    /// `expected_version`.
+    pub fn buy_nft(
+        ctx: Context<BuyNFT>,
+        quantity: u64,
+        expected_price: u64,
+        expected_version: Option<u64>,
+    ) -> Result<()> {
+        let listing = &ctx.accounts.listing;
         require!(listing.is_active, MarketplaceError::ListingNotActive);
+        require!(listing.price <= expected_price, MarketplaceError::ListingPriceChanged);
+        if let Some(expected_version) = expected_version {
+            require!(
+                listing.version == expected_version,
+                MarketplaceError::ListingVersionChanged
+            );
+        }
+        require!(
+            listing.asset_standard == AssetStandard::Legacy,
+            MarketplaceError::InvalidTokenStandard
+        );
+        require!(
+            quantity > 0 && quantity <= listing.quantity,
+            MarketplaceError::InvalidQuantity
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= listing.starts_at,
+            MarketplaceError::ListingNotStarted
+        );
+        if let Some(allowed_buyer) = listing.allowed_buyer {
+            require_keys_eq!(
+                allowed_buyer,
+                ctx.accounts.buyer.key(),
+                MarketplaceError::BuyerNotAllowed
+            );
+        }
     
-        let price = listing.price;
+        let price = listing.price.checked_mul(quantity).ok_or(MarketplaceError::MathOverflow)?;
+        let sold_out = quantity == listing.quantity;
+        let nft_mint = listing.nft_mint;
+        let marketplace_key = listing.marketplace;
+        let listing_bump = listing.bump;
         let marketplace = &ctx.accounts.marketplace;
         
         // Calculate fees
-        let marketplace_fee = (price as u128)
-            .checked_mul(marketplace.fee_basis_points as u128)
-            .unwrap()
-            .checked_div(10000)
-            .unwrap() as u64;
-        
-        let royalty_fee = (price as u128)
-            .checked_mul(listing.royalty_percentage as u128)
-            .unwrap()
-            .checked_div(10000)
-            .unwrap() as u64;
-        
-        let seller_amount = price
-            .checked_sub(marketplace_fee)
-            .unwrap()
-            .checked_sub(royalty_fee)
-            .unwrap();
+        let SaleSplit {
+            marketplace_fee,
+            royalty: royalty_fee,
+            seller_amount,
+        } = split_sale(price, marketplace.fee_basis_points, listing.royalty_percentage)?;
     
         // NEW: Verify Metaplex metadata standards for cross-marketplace compatibility
         verify_metaplex_metadata(
             &ctx.accounts.nft_metadata,
-            &listing.nft_mint,
+            &nft_mint,
         )?;
     
         // Transfer marketplace fee to treasury
-        **ctx.accounts.buyer.to_account_info().try_borrow_mut_lamports()? -= marketplace_fee;
-        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += marketplace_fee;
+        pay_from_buyer(
+            &ctx.accounts.buyer,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+            marketplace_fee,
+        )?;
     
         // Transfer royalty to creator
-        **ctx.accounts.buyer.to_account_info().try_borrow_mut_lamports()? -= royalty_fee;
-        **ctx.accounts.royalty_recipient.to_account_info().try_borrow_mut_lamports()? += royalty_fee;
+        pay_from_buyer(
+            &ctx.accounts.buyer,
+            &ctx.accounts.royalty_recipient,
+            &ctx.accounts.system_program,
+            royalty_fee,
+        )?;
     
         // Transfer remaining to seller
-        **ctx.accounts.buyer.to_account_info().try_borrow_mut_lamports()? -= seller_amount;
-        **ctx.accounts.seller.to_account_info().try_borrow_mut_lamports()? += seller_amount;
+        pay_from_buyer(
+            &ctx.accounts.buyer,
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.system_program,
+            seller_amount,
+        )?;
     
+        let seeds = &[
+            b"listing",
+            marketplace_key.as_ref(),
+            nft_mint.as_ref(),
+            &[listing_bump],
+        ];
+        let signer = &[&seeds[..]];
+
         // NEW: Check if this is a compressed NFT or standard NFT
         if ctx.accounts.is_compressed_nft.is_some() {
             // Handle compressed NFT transfer using Bubblegum
@@ -124,13 +247,6 @@ pub mod nft_marketplace {
             // In practice, you'd use Bubblegum's transfer instructions
         } else {
             // Standard NFT transfer (your existing logic)
-            let seeds = &[
-                b"listing",
-                listing.nft_mint.as_ref(),
-                &[listing.bump],
-            ];
-            let signer = &[&seeds[..]];
-    
             let cpi_accounts = Transfer {
                 from: ctx.accounts.escrow_nft_account.to_account_info(),
                 to: ctx.accounts.buyer_nft_account.to_account_info(),
@@ -138,41 +254,100 @@ pub mod nft_marketplace {
             };
             let cpi_program = ctx.accounts.token_program.to_account_info();
             let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-            token::transfer(cpi_ctx, 1)?;
+            token::transfer(cpi_ctx, quantity)?;
+
+            // Close the emptied escrow once the last unit is sold
+            if sold_out {
+                let cpi_accounts = CloseAccount {
+                    account: ctx.accounts.escrow_nft_account.to_account_info(),
+                    destination: ctx.accounts.seller.to_account_info(),
+                    authority: ctx.accounts.listing.to_account_info(),
+                };
+                let cpi_program = ctx.accounts.token_program.to_account_info();
+                let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+                token::close_account(cpi_ctx)?;
+            }
         }
     
         // Update listing status
-        listing.is_active = false;
+        if sold_out {
+            ctx.accounts.listing.is_active = false;
+            ctx.accounts.listing.close(ctx.accounts.seller.to_account_info())?;
+        } else {
+            ctx.accounts.listing.quantity -= quantity;
+        }
     
         // Update marketplace stats
         let marketplace = &mut ctx.accounts.marketplace;
-        marketplace.total_sales += 1;
+        marketplace.total_sales += quantity;
         marketplace.total_volume += price;
     
-        msg!("NFT sold for {} lamports with Metaplex standards verification", price);
+        msg!("{} unit(s) sold for {} lamports with Metaplex standards verification", quantity, price);
+        Ok(())
+    }
+
+    pub fn update_listing_price(ctx: Context<UpdateListingPrice>, new_price: u64) -> Result<()> {
+        require!(new_price > 0, MarketplaceError::InvalidPrice);
+
+        let listing = &mut ctx.accounts.listing;
+        listing.price = new_price;
+        listing.version += 1;
+
+        msg!("Listing price updated to {} lamports (version {})", new_price, listing.version);
         Ok(())
     }
 
+    pub fn list_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ListMany<'info>>,
+        prices: Vec<u64>,
+        royalty_percentage: u16,
+    ) -> Result<()> {
+        batch::list_many(ctx, prices, royalty_percentage)
+    }
+
+    pub fn cancel_many<'info>(ctx: Context<'_, '_, 'info, 'info, CancelMany<'info>>) -> Result<()> {
+        batch::cancel_many(ctx)
+    }
+
+    pub fn buy_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyMany<'info>>,
+        max_spend: u64,
+        skip_failures: bool,
+    ) -> Result<()> {
+        sweep::buy_many(ctx, max_spend, skip_failures)
+    }
+
     pub fn create_auction(
         ctx: Context<CreateAuction>,
         starting_price: u64,
         reserve_price: u64,
         duration: i64,
+        starts_at: Option<i64>,
+        buy_now_price: Option<u64>,
     ) -> Result<()> {
         require!(starting_price > 0, MarketplaceError::InvalidPrice);
         require!(reserve_price >= starting_price, MarketplaceError::InvalidReservePrice);
         require!(duration > 0, MarketplaceError::InvalidDuration);
+        if let Some(buy_now_price) = buy_now_price {
+            require!(buy_now_price >= reserve_price, MarketplaceError::InvalidBuyNowPrice);
+        }
+
+        // Duration runs from the scheduled start, not from creation
+        let start_time = resolve_start_time(starts_at, Clock::get()?.unix_timestamp)?;
 
         let auction = &mut ctx.accounts.auction;
+        auction.marketplace = ctx.accounts.marketplace.key();
         auction.seller = ctx.accounts.seller.key();
         auction.nft_mint = ctx.accounts.nft_mint.key();
         auction.starting_price = starting_price;
         auction.current_bid = 0;
         auction.reserve_price = reserve_price;
+        auction.buy_now_price = buy_now_price;
         auction.highest_bidder = None;
-        auction.start_time = Clock::get()?.unix_timestamp;
-        auction.end_time = auction.start_time + duration;
+        auction.start_time = start_time;
+        auction.end_time = start_time + duration;
         auction.is_active = true;
+        auction.asset_standard = AssetStandard::Legacy;
         auction.bump = ctx.bumps.auction;
 
         // Transfer NFT to escrow
@@ -190,24 +365,49 @@ pub mod nft_marketplace {
     }
 
     pub fn place_bid(ctx: Context<PlaceBid>, bid_amount: u64) -> Result<()> {
-        let auction = &mut ctx.accounts.auction;
+        let auction = &ctx.accounts.auction;
         let clock = Clock::get()?;
         
         require!(auction.is_active, MarketplaceError::AuctionNotActive);
+        require!(clock.unix_timestamp >= auction.start_time, MarketplaceError::AuctionNotStarted);
         require!(clock.unix_timestamp < auction.end_time, MarketplaceError::AuctionEnded);
         require!(bid_amount > auction.current_bid, MarketplaceError::BidTooLow);
         require!(bid_amount >= auction.starting_price, MarketplaceError::BidBelowStarting);
 
-        // Refund previous bidder if exists
+        // Credit the outbid amount to the previous bidder's refund account,
+        // only they can withdraw it. The first bid outbids nobody, so it must
+        // not create one.
+        require!(
+            auction.highest_bidder.is_some() == ctx.accounts.previous_bidder_refund.is_some(),
+            MarketplaceError::InvalidRefundAccount
+        );
         if let Some(previous_bidder) = auction.highest_bidder {
-            **ctx.accounts.auction.to_account_info().try_borrow_mut_lamports()? -= auction.current_bid;
-            **ctx.accounts.previous_bidder.to_account_info().try_borrow_mut_lamports()? += auction.current_bid;
+            let refund = auction.current_bid;
+            let auction_key = auction.key();
+            let auction_info = ctx.accounts.auction.to_account_info();
+
+            let bid_refund = ctx
+                .accounts
+                .previous_bidder_refund
+                .as_mut()
+                .ok_or(MarketplaceError::InvalidRefundAccount)?;
+            bid_refund.bidder = previous_bidder;
+            bid_refund.auction = auction_key;
+            bid_refund.amount = bid_refund.amount.checked_add(refund).ok_or(MarketplaceError::MathOverflow)?;
+            bid_refund.bump = ctx.bumps.previous_bidder_refund.ok_or(MarketplaceError::InvalidRefundAccount)?;
+
+            pay_from_escrow(&auction_info, &bid_refund.to_account_info(), refund)?;
         }
 
         // Escrow new bid
-        **ctx.accounts.bidder.to_account_info().try_borrow_mut_lamports()? -= bid_amount;
-        **ctx.accounts.auction.to_account_info().try_borrow_mut_lamports()? += bid_amount;
+        pay_from_buyer(
+            &ctx.accounts.bidder,
+            &ctx.accounts.auction.to_account_info(),
+            &ctx.accounts.system_program,
+            bid_amount,
+        )?;
 
+        let auction = &mut ctx.accounts.auction;
         auction.current_bid = bid_amount;
         auction.highest_bidder = Some(ctx.accounts.bidder.key());
 
@@ -215,41 +415,104 @@ pub mod nft_marketplace {
         Ok(())
     }
 
-    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
-        let auction = &mut ctx.accounts.auction;
+    pub fn withdraw_bid_refund(ctx: Context<WithdrawBidRefund>) -> Result<()> {
+        // Closing the refund account pays out the credited bids plus its rent
+        msg!("Withdrew {} lamports of outbid refunds", ctx.accounts.bid_refund.amount);
+        Ok(())
+    }
+
+    /// Permissionless: any keeper may settle an ended auction. The keeper
+    /// fronts the winner's ATA rent and is reimbursed plus a tip from the
+    /// proceeds.
+    pub fn settle_auction<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleAuction<'info>>,
+    ) -> Result<()> {
+        let auction = &ctx.accounts.auction;
         let clock = Clock::get()?;
         
         require!(auction.is_active, MarketplaceError::AuctionNotActive);
         require!(clock.unix_timestamp >= auction.end_time, MarketplaceError::AuctionNotEnded);
+        require!(
+            auction.asset_standard == AssetStandard::Legacy,
+            MarketplaceError::InvalidTokenStandard
+        );
 
-        if auction.current_bid >= auction.reserve_price && auction.highest_bidder.is_some() {
-            let marketplace = &ctx.accounts.marketplace;
-            let price = auction.current_bid;
-            
+        let price = auction.current_bid;
+        let nft_mint = auction.nft_mint;
+        let marketplace_key = auction.marketplace;
+        let bump = auction.bump;
+        let seeds = &[
+            b"auction",
+            marketplace_key.as_ref(),
+            nft_mint.as_ref(),
+            &[bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        if price >= auction.reserve_price && auction.highest_bidder.is_some() {
             // Calculate fees
-            let marketplace_fee = (price as u128)
-                .checked_mul(marketplace.fee_basis_points as u128)
-                .unwrap()
-                .checked_div(10000)
-                .unwrap() as u64;
-            
-            let seller_amount = price.checked_sub(marketplace_fee).unwrap();
+            let marketplace_fee = calculate_fee(price, ctx.accounts.marketplace.fee_basis_points)?;
+
+            // Same creator royalties as a fixed-price sale, paid out of the
+            // escrowed winning bid
+            let payouts = metadata_creator_payouts(
+                &ctx.accounts.nft_metadata,
+                &nft_mint,
+                price,
+                ctx.remaining_accounts,
+            )?;
+
+            let mut royalty_paid: u64 = 0;
+            for (creator, amount) in payouts {
+                pay_from_escrow(&ctx.accounts.auction.to_account_info(), creator, amount)?;
+                royalty_paid += amount;
+            }
+
+            // The keeper pays for the winner's ATA if it does not exist yet
+            let ata_rent = if ctx.accounts.winner_nft_account.data_is_empty() {
+                Rent::get()?.minimum_balance(TokenAccount::LEN)
+            } else {
+                0
+            };
+            let cpi_accounts = Create {
+                payer: ctx.accounts.keeper.to_account_info(),
+                associated_token: ctx.accounts.winner_nft_account.to_account_info(),
+                authority: ctx.accounts.winner.to_account_info(),
+                mint: ctx.accounts.nft_mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+            associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+
+            let keeper_amount = keeper_payout(
+                price,
+                ctx.accounts.marketplace.keeper_tip_basis_points,
+                ata_rent,
+                &[marketplace_fee, royalty_paid],
+            )?;
+            let seller_amount = seller_proceeds(price, &[marketplace_fee, royalty_paid, keeper_amount])?;
 
             // Transfer fees and payment
-            **ctx.accounts.auction.to_account_info().try_borrow_mut_lamports()? -= marketplace_fee;
-            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += marketplace_fee;
+            pay_from_escrow(
+                &ctx.accounts.auction.to_account_info(),
+                &ctx.accounts.treasury.to_account_info(),
+                marketplace_fee,
+            )?;
 
-            **ctx.accounts.auction.to_account_info().try_borrow_mut_lamports()? -= seller_amount;
-            **ctx.accounts.seller.to_account_info().try_borrow_mut_lamports()? += seller_amount;
+            pay_from_escrow(
+                &ctx.accounts.auction.to_account_info(),
+                &ctx.accounts.keeper.to_account_info(),
+                keeper_amount,
+            )?;
 
-            // Transfer NFT to winner
-            let seeds = &[
-                b"auction",
-                auction.nft_mint.as_ref(),
-                &[auction.bump],
-            ];
-            let signer = &[&seeds[..]];
+            pay_from_escrow(
+                &ctx.accounts.auction.to_account_info(),
+                &ctx.accounts.seller.to_account_info(),
+                seller_amount,
+            )?;
 
+            // Transfer NFT to winner
             let cpi_accounts = Transfer {
                 from: ctx.accounts.escrow_nft_account.to_account_info(),
                 to: ctx.accounts.winner_nft_account.to_account_info(),
@@ -259,46 +522,529 @@ pub mod nft_marketplace {
             let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
             token::transfer(cpi_ctx, 1)?;
 
-            msg!("Auction settled - NFT sold for {} lamports", price);
-        } else {
-            // Return NFT to seller if reserve not met
-            let seeds = &[
-                b"auction",
-                auction.nft_mint.as_ref(),
-                &[auction.bump],
-            ];
-            let signer = &[&seeds[..]];
-
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.escrow_nft_account.to_account_info(),
-                to: ctx.accounts.seller_nft_account.to_account_info(),
+            // Close the emptied escrow and free the auction PDA for this mint
+            let cpi_accounts = CloseAccount {
+                account: ctx.accounts.escrow_nft_account.to_account_info(),
+                destination: ctx.accounts.seller.to_account_info(),
                 authority: ctx.accounts.auction.to_account_info(),
             };
             let cpi_program = ctx.accounts.token_program.to_account_info();
             let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-            token::transfer(cpi_ctx, 1)?;
+            token::close_account(cpi_ctx)?;
+
+            let marketplace = &mut ctx.accounts.marketplace;
+            marketplace.total_sales += 1;
+            marketplace.total_volume += price;
+
+            ctx.accounts.auction.close(ctx.accounts.seller.to_account_info())?;
 
+            msg!("Auction settled - NFT sold for {} lamports", price);
+        } else {
             // Refund highest bidder
-            if auction.highest_bidder.is_some() {
-                **ctx.accounts.auction.to_account_info().try_borrow_mut_lamports()? -= auction.current_bid;
-                **ctx.accounts.winner.to_account_info().try_borrow_mut_lamports()? += auction.current_bid;
+            if ctx.accounts.auction.highest_bidder.is_some() {
+                pay_from_escrow(
+                    &ctx.accounts.auction.to_account_info(),
+                    &ctx.accounts.winner.to_account_info(),
+                    price,
+                )?;
             }
 
+            // The NFT stays in escrow so the seller can restart or cancel
+            let auction = &mut ctx.accounts.auction;
+            auction.current_bid = 0;
+            auction.highest_bidder = None;
+            auction.is_active = false;
+
             msg!("Auction ended - reserve price not met");
         }
 
-        auction.is_active = false;
         Ok(())
     }
+
+    pub fn cancel_auction(ctx: Context<CancelAuction>) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(auction.highest_bidder.is_none(), MarketplaceError::AuctionHasBids);
+        require!(
+            auction.asset_standard == AssetStandard::Legacy,
+            MarketplaceError::InvalidTokenStandard
+        );
+
+        let seeds = &[
+            b"auction",
+            auction.marketplace.as_ref(),
+            auction.nft_mint.as_ref(),
+            &[auction.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Return NFT to seller
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_nft_account.to_account_info(),
+            to: ctx.accounts.seller_nft_account.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.escrow_nft_account.to_account_info(),
+            destination: ctx.accounts.seller.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+
+        msg!("Auction cancelled");
+        Ok(())
+    }
+
+    pub fn restart_auction(
+        ctx: Context<RestartAuction>,
+        starting_price: u64,
+        reserve_price: u64,
+        duration: i64,
+        starts_at: Option<i64>,
+        buy_now_price: Option<u64>,
+    ) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        // Only an auction that ended below its reserve can be run again;
+        // Core auctions have no token escrow and are cancelled instead
+        require!(!auction.is_active, MarketplaceError::AuctionNotEnded);
+        require!(auction.highest_bidder.is_none(), MarketplaceError::AuctionHasBids);
+        require!(
+            auction.asset_standard == AssetStandard::Legacy,
+            MarketplaceError::InvalidTokenStandard
+        );
+        require!(starting_price > 0, MarketplaceError::InvalidPrice);
+        require!(reserve_price >= starting_price, MarketplaceError::InvalidReservePrice);
+        require!(duration > 0, MarketplaceError::InvalidDuration);
+        if let Some(buy_now_price) = buy_now_price {
+            require!(buy_now_price >= reserve_price, MarketplaceError::InvalidBuyNowPrice);
+        }
+
+        let start_time = resolve_start_time(starts_at, Clock::get()?.unix_timestamp)?;
+
+        // Same PDA and escrow, fresh terms
+        let auction = &mut ctx.accounts.auction;
+        auction.starting_price = starting_price;
+        auction.current_bid = 0;
+        auction.reserve_price = reserve_price;
+        auction.buy_now_price = buy_now_price;
+        auction.start_time = start_time;
+        auction.end_time = start_time + duration;
+        auction.is_active = true;
+
+        msg!("Auction restarted with starting price {} lamports", starting_price);
+        Ok(())
+    }
+
+    pub fn buy_now<'info>(ctx: Context<'_, '_, 'info, 'info, BuyNow<'info>>) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        let clock = Clock::get()?;
+
+        require!(auction.is_active, MarketplaceError::AuctionNotActive);
+        require!(
+            auction.asset_standard == AssetStandard::Legacy,
+            MarketplaceError::InvalidTokenStandard
+        );
+        require!(clock.unix_timestamp >= auction.start_time, MarketplaceError::AuctionNotStarted);
+        require!(clock.unix_timestamp < auction.end_time, MarketplaceError::AuctionEnded);
+
+        let price = auction.buy_now_price.ok_or(MarketplaceError::BuyNowUnavailable)?;
+        require!(auction.current_bid < price, MarketplaceError::BuyNowUnavailable);
+
+        let nft_mint = auction.nft_mint;
+        let marketplace_key = auction.marketplace;
+        let auction_bump = auction.bump;
+
+        // Refund the current highest bidder from the auction escrow
+        if auction.highest_bidder.is_some() {
+            let refund = auction.current_bid;
+            pay_from_escrow(
+                &ctx.accounts.auction.to_account_info(),
+                &ctx.accounts.previous_bidder,
+                refund,
+            )?;
+        }
+
+        // Calculate fees
+        let marketplace_fee = calculate_fee(price, ctx.accounts.marketplace.fee_basis_points)?;
+
+        // Creator royalties come from the NFT's metadata, creators follow as
+        // remaining accounts in metadata order
+        let payouts = metadata_creator_payouts(
+            &ctx.accounts.nft_metadata,
+            &nft_mint,
+            price,
+            ctx.remaining_accounts,
+        )?;
+
+        let mut royalty_paid: u64 = 0;
+        for (creator, amount) in payouts {
+            pay_from_buyer(&ctx.accounts.buyer, creator, &ctx.accounts.system_program, amount)?;
+            royalty_paid += amount;
+        }
+
+        let seller_amount = seller_proceeds(price, &[marketplace_fee, royalty_paid])?;
+
+        pay_from_buyer(
+            &ctx.accounts.buyer,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+            marketplace_fee,
+        )?;
+        pay_from_buyer(
+            &ctx.accounts.buyer,
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.system_program,
+            seller_amount,
+        )?;
+
+        // Transfer NFT to buyer
+        let seeds = &[
+            b"auction",
+            marketplace_key.as_ref(),
+            nft_mint.as_ref(),
+            &[auction_bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_nft_account.to_account_info(),
+            to: ctx.accounts.buyer_nft_account.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        // Close the emptied escrow and free the auction PDA for this mint
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.escrow_nft_account.to_account_info(),
+            destination: ctx.accounts.seller.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+
+        let marketplace = &mut ctx.accounts.marketplace;
+        marketplace.total_sales += 1;
+        marketplace.total_volume += price;
+
+        ctx.accounts.auction.close(ctx.accounts.seller.to_account_info())?;
+
+        msg!("Auction ended by buy-now at {} lamports", price);
+        Ok(())
+    }
+
+    pub fn create_token_auction(
+        ctx: Context<CreateTokenAuction>,
+        starting_price: u64,
+        reserve_price: u64,
+        duration: i64,
+        starts_at: Option<i64>,
+    ) -> Result<()> {
+        token_auction::create_token_auction(ctx, starting_price, reserve_price, duration, starts_at)
+    }
+
+    pub fn place_token_bid(ctx: Context<PlaceTokenBid>, bid_amount: u64) -> Result<()> {
+        token_auction::place_token_bid(ctx, bid_amount)
+    }
+
+    pub fn settle_token_auction<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleTokenAuction<'info>>,
+    ) -> Result<()> {
+        token_auction::settle_token_auction(ctx)
+    }
+
+    pub fn withdraw_token_bid_refund(ctx: Context<WithdrawTokenBidRefund>) -> Result<()> {
+        token_auction::withdraw_token_bid_refund(ctx)
+    }
+
+    pub fn cancel_token_auction(ctx: Context<CancelTokenAuction>) -> Result<()> {
+        token_auction::cancel_token_auction(ctx)
+    }
+
+    pub fn create_multi_unit_auction(
+        ctx: Context<CreateMultiUnitAuction>,
+        quantity: u64,
+        starting_price: u64,
+        duration: i64,
+        starts_at: Option<i64>,
+    ) -> Result<()> {
+        multi_unit_auction::create_multi_unit_auction(ctx, quantity, starting_price, duration, starts_at)
+    }
+
+    pub fn place_unit_bid(ctx: Context<PlaceUnitBid>, bid_amount: u64) -> Result<()> {
+        multi_unit_auction::place_unit_bid(ctx, bid_amount)
+    }
+
+    pub fn settle_multi_unit_auction<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleMultiUnitAuction<'info>>,
+        max_winners: u8,
+    ) -> Result<()> {
+        multi_unit_auction::settle_multi_unit_auction(ctx, max_winners)
+    }
+
+    pub fn create_candle_auction(
+        ctx: Context<CreateCandleAuction>,
+        starting_price: u64,
+        duration: i64,
+        closing_window: i64,
+        starts_at: Option<i64>,
+    ) -> Result<()> {
+        candle_auction::create_candle_auction(ctx, starting_price, duration, closing_window, starts_at)
+    }
+
+    pub fn place_candle_bid(ctx: Context<PlaceCandleBid>, bid_amount: u64) -> Result<()> {
+        candle_auction::place_candle_bid(ctx, bid_amount)
+    }
+
+    pub fn commit_candle_settlement(ctx: Context<CommitCandleSettlement>) -> Result<()> {
+        candle_auction::commit_candle_settlement(ctx)
+    }
+
+    pub fn settle_candle_auction<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleCandleAuction<'info>>,
+    ) -> Result<()> {
+        candle_auction::settle_candle_auction(ctx)
+    }
+
+    pub fn claim_candle_refund(ctx: Context<ClaimCandleRefund>, snapshot_index: u8) -> Result<()> {
+        candle_auction::claim_candle_refund(ctx, snapshot_index)
+    }
+
+    pub fn list_core_asset(ctx: Context<ListCoreAsset>, price: u64) -> Result<()> {
+        core_asset::list_core_asset(ctx, price)
+    }
+
+    pub fn buy_core_asset<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyCoreAsset<'info>>,
+    ) -> Result<()> {
+        core_asset::buy_core_asset(ctx)
+    }
+
+    pub fn create_core_auction(
+        ctx: Context<CreateCoreAuction>,
+        starting_price: u64,
+        reserve_price: u64,
+        duration: i64,
+        starts_at: Option<i64>,
+    ) -> Result<()> {
+        core_asset::create_core_auction(ctx, starting_price, reserve_price, duration, starts_at)
+    }
+
+    pub fn settle_core_auction<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleCoreAuction<'info>>,
+    ) -> Result<()> {
+        core_asset::settle_core_auction(ctx)
+    }
+
+    pub fn cancel_core_listing(ctx: Context<CancelCoreListing>) -> Result<()> {
+        core_asset::cancel_core_listing(ctx)
+    }
+
+    pub fn cancel_core_auction(ctx: Context<CancelCoreAuction>) -> Result<()> {
+        core_asset::cancel_core_auction(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn mint_nft(
+        ctx: Context<MintNFT>,
+        name: String,
+        symbol: String,
+        uri: String,
+        seller_fee_basis_points: u16,
+        creators: Option<Vec<Creator>>,
+        collection: Option<Pubkey>,
+        is_mutable: bool,
+        max_supply: Option<u64>,
+    ) -> Result<()> {
+        metaplex_standards::mint_nft(
+            ctx,
+            name,
+            symbol,
+            uri,
+            seller_fee_basis_points,
+            creators,
+            collection,
+            is_mutable,
+            max_supply,
+        )
+    }
+
+    pub fn create_collection(
+        ctx: Context<CreateCollection>,
+        name: String,
+        symbol: String,
+        uri: String,
+        seller_fee_basis_points: u16,
+    ) -> Result<()> {
+        collection::create_collection(ctx, name, symbol, uri, seller_fee_basis_points)
+    }
+
+    pub fn verify_collection_item(ctx: Context<VerifyCollectionItem>) -> Result<()> {
+        collection::verify_collection_item(ctx)
+    }
+
+    pub fn sell_edition(
+        ctx: Context<SellEdition>,
+        price: u64,
+        max_prints: u64,
+    ) -> Result<()> {
+        editions::sell_edition(ctx, price, max_prints)
+    }
+
+    pub fn buy_edition<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyEdition<'info>>,
+    ) -> Result<()> {
+        editions::buy_edition(ctx)
+    }
+
+    pub fn cancel_edition_sale(ctx: Context<CancelEditionSale>) -> Result<()> {
+        editions::cancel_edition_sale(ctx)
+    }
+
+    pub fn list_bundle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ListBundle<'info>>,
+        bundle_id: u64,
+        item_prices: Vec<u64>,
+    ) -> Result<()> {
+        bundle::list_bundle(ctx, bundle_id, item_prices)
+    }
+
+    pub fn buy_bundle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyBundle<'info>>,
+    ) -> Result<()> {
+        bundle::buy_bundle(ctx)
+    }
+
+    pub fn cancel_bundle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelBundle<'info>>,
+    ) -> Result<()> {
+        bundle::cancel_bundle(ctx)
+    }
+
+    pub fn propose_swap(
+        ctx: Context<ProposeSwap>,
+        requested_mint: Pubkey,
+        proposer_lamports: u64,
+        counterparty_lamports: u64,
+        counterparty: Option<Pubkey>,
+    ) -> Result<()> {
+        swap::propose_swap(
+            ctx,
+            requested_mint,
+            proposer_lamports,
+            counterparty_lamports,
+            counterparty,
+        )
+    }
+
+    pub fn accept_swap(ctx: Context<AcceptSwap>) -> Result<()> {
+        swap::accept_swap(ctx)
+    }
+
+    pub fn cancel_swap(ctx: Context<CancelSwap>) -> Result<()> {
+        swap::cancel_swap(ctx)
+    }
+
+    pub fn create_deal(
+        ctx: Context<CreateDeal>,
+        deal_id: u64,
+        taker: Pubkey,
+        maker_assets: Vec<DealAsset>,
+        taker_assets: Vec<DealAsset>,
+        expires_at: i64,
+    ) -> Result<()> {
+        deal::create_deal(ctx, deal_id, taker, maker_assets, taker_assets, expires_at)
+    }
+
+    pub fn deposit_deal_leg<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DepositDealLeg<'info>>,
+    ) -> Result<()> {
+        deal::deposit_deal_leg(ctx)
+    }
+
+    pub fn execute_deal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteDeal<'info>>,
+    ) -> Result<()> {
+        deal::execute_deal(ctx)
+    }
+
+    pub fn withdraw_deal_leg<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawDealLeg<'info>>,
+    ) -> Result<()> {
+        deal::withdraw_deal_leg(ctx)
+    }
+
+    pub fn cancel_deal(ctx: Context<CancelDeal>) -> Result<()> {
+        deal::cancel_deal(ctx)
+    }
+}
+
+/// Scheduled listings and auctions may start later, but never in the past.
+pub(crate) fn resolve_start_time(starts_at: Option<i64>, now: i64) -> Result<i64> {
+    match starts_at {
+        Some(starts_at) => {
+            require!(starts_at >= now, MarketplaceError::InvalidStartTime);
+            Ok(starts_at)
+        }
+        None => Ok(now),
+    }
+}
+
+/// Pays `amount` out of a signer's wallet through the System Program. The
+/// runtime only lets the owning program debit an account, so lamport
+/// arithmetic is reserved for this program's escrow PDAs.
+pub(crate) fn pay_from_buyer<'info>(
+    buyer: &Signer<'info>,
+    recipient: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let cpi_accounts = system_program::Transfer {
+        from: buyer.to_account_info(),
+        to: recipient.clone(),
+    };
+    let cpi_ctx = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+    system_program::transfer(cpi_ctx, amount)
+}
+
+/// Moves `amount` out of one of this program's escrow PDAs by lamport
+/// arithmetic. Fails without touching either balance if the escrow holds
+/// less than `amount`.
+pub(crate) fn pay_from_escrow(escrow: &AccountInfo, recipient: &AccountInfo, amount: u64) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let escrow_balance = escrow
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(MarketplaceError::EscrowUnderfunded)?;
+    let recipient_balance = recipient
+        .lamports()
+        .checked_add(amount)
+        .ok_or(MarketplaceError::MathOverflow)?;
+    **escrow.try_borrow_mut_lamports()? = escrow_balance;
+    **recipient.try_borrow_mut_lamports()? = recipient_balance;
+    Ok(())
 }
 
 #[derive(Accounts)]
+#[instruction(name: String)]
 pub struct InitializeMarketplace<'info> {
     #[account(
         init,
         payer = authority,
         space = 8 + Marketplace::LEN,
-        seeds = [b"marketplace"],
+        seeds = [b"marketplace", authority.key().as_ref(), name.as_bytes()],
         bump
     )]
     pub marketplace: Account<'info, Marketplace>,
@@ -314,11 +1060,17 @@ pub struct InitializeMarketplace<'info> {
 
 #[derive(Accounts)]
 pub struct ListNFT<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+    
     #[account(
         init,
         payer = seller,
         space = 8 + Listing::LEN,
-        seeds = [b"listing", nft_mint.key().as_ref()],
+        seeds = [b"listing", marketplace.key().as_ref(), nft_mint.key().as_ref()],
         bump
     )]
     pub listing: Account<'info, Listing>,
@@ -331,8 +1083,7 @@ pub struct ListNFT<'info> {
     #[account(
         mut,
         constraint = seller_nft_account.mint == nft_mint.key(),
-        constraint = seller_nft_account.owner == seller.key(),
-        constraint = seller_nft_account.amount == 1
+        constraint = seller_nft_account.owner == seller.key()
     )]
     pub seller_nft_account: Account<'info, TokenAccount>,
     
@@ -353,15 +1104,16 @@ pub struct ListNFT<'info> {
 pub struct BuyNFT<'info> {
     #[account(
         mut,
-        seeds = [b"marketplace"],
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
         bump = marketplace.bump
     )]
     pub marketplace: Account<'info, Marketplace>,
     
     #[account(
         mut,
-        seeds = [b"listing", listing.nft_mint.as_ref()],
+        seeds = [b"listing", marketplace.key().as_ref(), listing.nft_mint.as_ref()],
         bump = listing.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
         constraint = listing.is_active
     )]
     pub listing: Account<'info, Listing>,
@@ -369,20 +1121,23 @@ pub struct BuyNFT<'info> {
     #[account(mut)]
     pub buyer: Signer<'info>,
     
-    #[account(mut)]
+    #[account(mut, constraint = seller.key() == listing.seller)]
     pub seller: SystemAccount<'info>,
     
     #[account(
         mut,
         constraint = escrow_nft_account.mint == listing.nft_mint,
-        constraint = escrow_nft_account.amount == 1
+        constraint = escrow_nft_account.amount >= listing.quantity
     )]
     pub escrow_nft_account: Account<'info, TokenAccount>,
     
+    #[account(address = listing.nft_mint)]
+    pub nft_mint: Account<'info, token::Mint>,
+
     #[account(
         init_if_needed,
         payer = buyer,
-        associated_token::mint = listing.nft_mint,
+        associated_token::mint = nft_mint,
         associated_token::authority = buyer
     )]
     pub buyer_nft_account: Account<'info, TokenAccount>,
@@ -409,43 +1164,39 @@ pub struct BuyNFT<'info> {
 }
 
 #[derive(Accounts)]
-pub struct CancelListing<'info> {
+pub struct UpdateListingPrice<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
     #[account(
         mut,
-        seeds = [b"listing", listing.nft_mint.as_ref()],
+        seeds = [b"listing", marketplace.key().as_ref(), listing.nft_mint.as_ref()],
         bump = listing.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
         constraint = listing.seller == seller.key(),
         constraint = listing.is_active
     )]
     pub listing: Account<'info, Listing>,
     
-    #[account(mut)]
     pub seller: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = escrow_nft_account.mint == listing.nft_mint,
-        constraint = escrow_nft_account.amount == 1
-    )]
-    pub escrow_nft_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        constraint = seller_nft_account.mint == listing.nft_mint,
-        constraint = seller_nft_account.owner == seller.key()
-    )]
-    pub seller_nft_account: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
 pub struct CreateAuction<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+    
     #[account(
         init,
         payer = seller,
         space = 8 + Auction::LEN,
-        seeds = [b"auction", nft_mint.key().as_ref()],
+        seeds = [b"auction", marketplace.key().as_ref(), nft_mint.key().as_ref()],
         bump
     )]
     pub auction: Account<'info, Auction>,
@@ -478,121 +1229,345 @@ pub struct CreateAuction<'info> {
 
 #[derive(Accounts)]
 pub struct PlaceBid<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
     #[account(
         mut,
-        seeds = [b"auction", auction.nft_mint.as_ref()],
-        bump = auction.bump
+        seeds = [b"auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
     )]
     pub auction: Account<'info, Auction>,
     
     #[account(mut)]
     pub bidder: Signer<'info>,
     
-    /// CHECK: Previous bidder (optional)
-    #[account(mut)]
+    /// CHECK: Current highest bidder, or the bidder itself on the first bid
+    #[account(
+        constraint = auction.highest_bidder.is_none_or(|bidder| bidder == previous_bidder.key())
+            @ MarketplaceError::InvalidWinner
+    )]
     pub previous_bidder: AccountInfo<'info>,
     
+    /// Refund account of the outbid bidder; omitted on the first bid
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        space = 8 + BidRefund::LEN,
+        seeds = [b"bid_refund", auction.key().as_ref(), previous_bidder.key().as_ref()],
+        bump
+    )]
+    pub previous_bidder_refund: Option<Account<'info, BidRefund>>,
+    
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct WithdrawBidRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"bid_refund", bid_refund.auction.as_ref(), bidder.key().as_ref()],
+        bump = bid_refund.bump,
+        constraint = bid_refund.bidder == bidder.key(),
+        close = bidder
+    )]
+    pub bid_refund: Account<'info, BidRefund>,
+    
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct SettleAuction<'info> {
     #[account(
         mut,
-        seeds = [b"marketplace"],
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
         bump = marketplace.bump
     )]
     pub marketplace: Account<'info, Marketplace>,
     
     #[account(
         mut,
-        seeds = [b"auction", auction.nft_mint.as_ref()],
-        bump = auction.bump
+        seeds = [b"auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
     )]
     pub auction: Account<'info, Auction>,
     
-    #[account(mut)]
+    #[account(mut, constraint = seller.key() == auction.seller)]
     pub seller: SystemAccount<'info>,
     
     /// CHECK: Winner (highest bidder)
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = auction.highest_bidder.is_none_or(|bidder| bidder == winner.key())
+            @ MarketplaceError::InvalidWinner
+    )]
     pub winner: AccountInfo<'info>,
     
+    /// Whoever runs settlement; reimbursed for the winner's ATA plus a tip
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    
+    #[account(address = auction.nft_mint)]
+    pub nft_mint: Account<'info, token::Mint>,
+    
     #[account(
         mut,
         constraint = escrow_nft_account.mint == auction.nft_mint
     )]
     pub escrow_nft_account: Account<'info, TokenAccount>,
     
+    /// CHECK: Winner's ATA, created by the keeper during settlement if missing
     #[account(
-        init_if_needed,
-        payer = winner,
-        associated_token::mint = auction.nft_mint,
-        associated_token::authority = winner
+        mut,
+        address = associated_token::get_associated_token_address(&winner.key(), &auction.nft_mint)
+    )]
+    pub winner_nft_account: AccountInfo<'info>,
+    
+    /// CHECK: Metadata PDA of the auctioned mint, empty if the mint has no
+    /// metadata; creators to pay follow as remaining accounts in metadata order
+    #[account(
+        seeds = [b"metadata", mpl_token_metadata::ID.as_ref(), auction.nft_mint.as_ref()],
+        bump,
+        seeds::program = mpl_token_metadata::ID
+    )]
+    pub nft_metadata: AccountInfo<'info>,
+    
+    /// CHECK: Treasury account
+    #[account(mut, constraint = treasury.key() == marketplace.treasury)]
+    pub treasury: AccountInfo<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAuction<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
     )]
-    pub winner_nft_account: Account<'info, TokenAccount>,
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = auction.seller == seller.key(),
+        close = seller
+    )]
+    pub auction: Account<'info, Auction>,
+    
+    #[account(mut)]
+    pub seller: Signer<'info>,
     
     #[account(
         mut,
-        constraint = seller_nft_account.mint == auction.nft_mint
+        constraint = escrow_nft_account.mint == auction.nft_mint,
+        constraint = escrow_nft_account.owner == auction.key(),
+        constraint = escrow_nft_account.amount == 1
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == auction.nft_mint,
+        constraint = seller_nft_account.owner == seller.key()
     )]
     pub seller_nft_account: Account<'info, TokenAccount>,
     
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RestartAuction<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = auction.seller == seller.key()
+    )]
+    pub auction: Account<'info, Auction>,
+    
+    pub seller: Signer<'info>,
+    
+    /// The NFT must still be escrowed from the previous run
+    #[account(
+        constraint = escrow_nft_account.mint == auction.nft_mint,
+        constraint = escrow_nft_account.owner == auction.key(),
+        constraint = escrow_nft_account.amount == 1
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct BuyNow<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub auction: Account<'info, Auction>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mut, constraint = seller.key() == auction.seller)]
+    pub seller: SystemAccount<'info>,
+
+    /// CHECK: Current highest bidder to refund, checked against the auction
+    #[account(
+        mut,
+        constraint = auction.highest_bidder.is_none_or(|bidder| bidder == previous_bidder.key())
+            @ MarketplaceError::InvalidWinner
+    )]
+    pub previous_bidder: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_nft_account.mint == auction.nft_mint,
+        constraint = escrow_nft_account.owner == auction.key()
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    #[account(address = auction.nft_mint)]
+    pub nft_mint: Account<'info, token::Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = buyer
+    )]
+    pub buyer_nft_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Metaplex metadata account, deserialized for creator royalties
+    pub nft_metadata: AccountInfo<'info>,
+
     /// CHECK: Treasury account
     #[account(mut, constraint = treasury.key() == marketplace.treasury)]
     pub treasury: AccountInfo<'info>,
-    
+
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
+/// Longest storefront name; the name is a PDA seed, and seeds are capped at
+/// 32 bytes.
+pub const MAX_MARKETPLACE_NAME_LEN: usize = 32;
+
 #[account]
 pub struct Marketplace {
+    /// Key that created the storefront; with `name`, it seeds the PDA
+    pub creator: Pubkey,
+    pub name: String,
     pub authority: Pubkey,
+    /// Nominee that must accept before becoming the authority
+    pub pending_authority: Option<Pubkey>,
     pub treasury: Pubkey,
     pub fee_basis_points: u16,
+    /// Share of auction proceeds paid to whoever cranks settlement
+    pub keeper_tip_basis_points: u16,
     pub total_sales: u64,
     pub total_volume: u64,
     pub bump: u8,
 }
 
 impl Marketplace {
-    pub const LEN: usize = 32 + 32 + 2 + 8 + 8 + 1;
+    pub const LEN: usize =
+        32 + (4 + MAX_MARKETPLACE_NAME_LEN) + 32 + 33 + 32 + 2 + 2 + 8 + 8 + 1;
 }
 
 #[account]
 pub struct Listing {
+    pub marketplace: Pubkey,
     pub seller: Pubkey,
     pub nft_mint: Pubkey,
+    /// Price per unit in lamports
     pub price: u64,
+    /// Units still held in escrow
+    pub quantity: u64,
     pub royalty_percentage: u16,
     pub royalty_recipient: Pubkey,
+    /// Private sale: only this buyer may purchase
+    pub allowed_buyer: Option<Pubkey>,
     pub is_active: bool,
     pub listed_at: i64,
+    pub starts_at: i64,
+    pub asset_standard: AssetStandard,
+    /// Bumped whenever the seller changes the listing terms
+    pub version: u64,
     pub bump: u8,
 }
 
 impl Listing {
-    pub const LEN: usize = 32 + 32 + 8 + 2 + 32 + 1 + 8 + 1;
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 2 + 32 + (1 + 32) + 1 + 8 + 8 + 1 + 8 + 1;
 }
 
 #[account]
 pub struct Auction {
+    pub marketplace: Pubkey,
     pub seller: Pubkey,
     pub nft_mint: Pubkey,
     pub starting_price: u64,
     pub current_bid: u64,
     pub reserve_price: u64,
+    pub buy_now_price: Option<u64>,
     pub highest_bidder: Option<Pubkey>,
     pub start_time: i64,
     pub end_time: i64,
     pub is_active: bool,
+    pub asset_standard: AssetStandard,
     pub bump: u8,
 }
 
 impl Auction {
-    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + (1 + 32) + 8 + 8 + 1 + 1;
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8 + (1 + 8) + (1 + 32) + 8 + 8 + 1 + 1 + 1;
+}
+
+/// Outbid funds owed to a bidder on an auction, withdrawn by the bidder
+#[account]
+pub struct BidRefund {
+    pub bidder: Pubkey,
+    pub auction: Pubkey,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl BidRefund {
+    pub const LEN: usize = 32 + 32 + 8 + 1;
+}
+
+/// Which Metaplex standard the escrowed asset follows. Legacy assets are
+/// SPL mints held in an escrow token account; Core assets are single
+/// accounts owned directly by the listing or auction PDA.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AssetStandard {
+    Legacy,
+    Core,
 }
 
 #[error_code]
@@ -626,4 +1601,174 @@ pub enum MarketplaceError {
     InvalidTokenStandard,
     #[msg("Compressed NFT transfer failed")]
     CompressedNFTTransferFailed,
-}
\ No newline at end of file
+    #[msg("Invalid Metaplex Core asset")]
+    InvalidCoreAsset,
+    #[msg("Creator account does not match the royalty creators")]
+    CreatorMismatch,
+    #[msg("Winner does not match the highest bidder")]
+    InvalidWinner,
+    #[msg("Print cap must be greater than zero")]
+    InvalidPrintCap,
+    #[msg("All prints of this edition have been sold")]
+    EditionSoldOut,
+    #[msg("Invalid quantity")]
+    InvalidQuantity,
+    #[msg("Bundle item accounts are missing or invalid")]
+    InvalidBundleAccounts,
+    #[msg("Bundle exceeds the maximum number of items")]
+    BundleTooLarge,
+    #[msg("Invalid swap terms")]
+    InvalidSwapTerms,
+    #[msg("Signer is not the designated counterparty")]
+    UnauthorizedCounterparty,
+    #[msg("Invalid deal terms")]
+    InvalidDealTerms,
+    #[msg("Signer is not a party to this deal")]
+    UnauthorizedDealParty,
+    #[msg("Deal asset accounts are missing or invalid")]
+    InvalidDealAccounts,
+    #[msg("Deal leg has already been funded")]
+    DealLegAlreadyFunded,
+    #[msg("Deal is not fully funded")]
+    DealNotFunded,
+    #[msg("Deal has not reached its timeout")]
+    DealNotExpired,
+    #[msg("Listing is reserved for a different buyer")]
+    BuyerNotAllowed,
+    #[msg("Start time cannot be in the past")]
+    InvalidStartTime,
+    #[msg("Listing has not started yet")]
+    ListingNotStarted,
+    #[msg("Auction has not started yet")]
+    AuctionNotStarted,
+    #[msg("Buy-now price must be at least the reserve price")]
+    InvalidBuyNowPrice,
+    #[msg("Buy-now is not available for this auction")]
+    BuyNowUnavailable,
+    #[msg("Auction already has bids")]
+    AuctionHasBids,
+    #[msg("Token account does not match the payment mint")]
+    InvalidPaymentMint,
+    #[msg("Auction winner accounts are missing or invalid")]
+    InvalidAuctionAccounts,
+    #[msg("Candle settlement slot is not committed or not reached yet")]
+    CandleSlotNotReady,
+    #[msg("Refund has already been claimed")]
+    RefundAlreadyClaimed,
+    #[msg("Sweep listing accounts are missing or invalid")]
+    InvalidSweepAccounts,
+    #[msg("Listing price exceeds the remaining sweep budget")]
+    SweepBudgetExceeded,
+    #[msg("Batch item accounts are missing or invalid")]
+    InvalidBatchAccounts,
+    #[msg("Listing price is higher than expected")]
+    ListingPriceChanged,
+    #[msg("Listing was modified since the expected version")]
+    ListingVersionChanged,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Fees and royalties exceed the sale price")]
+    FeesExceedPrice,
+    #[msg("Signer is not the marketplace authority")]
+    UnauthorizedAuthority,
+    #[msg("Signer is not the pending marketplace authority")]
+    NotPendingAuthority,
+    #[msg("Marketplace name must be 1 to 32 bytes")]
+    InvalidMarketplaceName,
+    #[msg("Account belongs to a different marketplace")]
+    MarketplaceMismatch,
+    #[msg("Committed slot hash is no longer available; commit again")]
+    CandleSlotExpired,
+    #[msg("Royalty rule set does not allow this marketplace to transfer the asset")]
+    RoyaltyRuleSetViolation,
+    #[msg("Escrow holds less than the amount to pay out")]
+    EscrowUnderfunded,
+    #[msg("Refund account must be passed exactly when a bid is outbid")]
+    InvalidRefundAccount,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RENT: u64 = 1_705_200;
+
+    fn escrow_payout(escrow_lamports: u64, amounts: &[u64]) -> (u64, Vec<u64>, Result<()>) {
+        let owner = crate::ID;
+        let escrow_key = Pubkey::new_unique();
+        let mut escrow_lamports = escrow_lamports;
+        let mut escrow_data = [];
+        let escrow = AccountInfo::new(
+            &escrow_key,
+            false,
+            true,
+            &mut escrow_lamports,
+            &mut escrow_data,
+            &owner,
+            false,
+            0,
+        );
+
+        let keys: Vec<Pubkey> = amounts.iter().map(|_| Pubkey::new_unique()).collect();
+        let mut balances = vec![0u64; amounts.len()];
+        let mut result = Ok(());
+        for ((key, balance), amount) in keys.iter().zip(balances.iter_mut()).zip(amounts) {
+            let mut data = [];
+            let system = System::id();
+            let recipient = AccountInfo::new(key, false, true, balance, &mut data, &system, false, 0);
+            result = pay_from_escrow(&escrow, &recipient, *amount);
+            if result.is_err() {
+                break;
+            }
+        }
+        let escrow_left = escrow.lamports();
+        (escrow_left, balances, result)
+    }
+
+    #[test]
+    fn pay_from_escrow_moves_lamports() {
+        let (escrow, paid, result) = escrow_payout(RENT + 500, &[200, 0, 300]);
+        result.unwrap();
+        assert_eq!(escrow, RENT);
+        assert_eq!(paid, vec![200, 0, 300]);
+    }
+
+    #[test]
+    fn pay_from_escrow_rejects_overdraft_without_moving_lamports() {
+        let (escrow, paid, result) = escrow_payout(100, &[101]);
+        assert_eq!(result.unwrap_err(), MarketplaceError::EscrowUnderfunded.into());
+        assert_eq!(escrow, 100);
+        assert_eq!(paid, vec![0]);
+    }
+
+    #[test]
+    fn settle_auction_pays_out_exactly_the_winning_bid() {
+        // Same order as `settle_auction`: royalty, fee, keeper, seller
+        for price in [1_000_000_000, 1_000_000, 1] {
+            let royalty = calculate_fee(price, 500).unwrap();
+            let marketplace_fee = calculate_fee(price, 250).unwrap();
+            let keeper_amount =
+                keeper_payout(price, 100, 2_039_280, &[marketplace_fee, royalty]).unwrap();
+            let seller_amount =
+                seller_proceeds(price, &[marketplace_fee, royalty, keeper_amount]).unwrap();
+
+            let (escrow, paid, result) = escrow_payout(
+                RENT + price,
+                &[royalty, marketplace_fee, keeper_amount, seller_amount],
+            );
+            result.unwrap();
+            // Only the rent is left, for the close to return to the seller
+            assert_eq!(escrow, RENT);
+            assert_eq!(paid.iter().sum::<u64>(), price);
+        }
+    }
+
+    #[test]
+    fn buy_now_refund_returns_the_whole_outbid_amount() {
+        let current_bid = 750_000_000;
+        let (escrow, paid, result) = escrow_payout(RENT + current_bid, &[current_bid]);
+        result.unwrap();
+        assert_eq!(escrow, RENT);
+        assert_eq!(paid, vec![current_bid]);
+    }
+}
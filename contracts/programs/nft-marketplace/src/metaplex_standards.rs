@@ -1,9 +1,18 @@
 use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount};
 use mpl_token_metadata::{
-    instructions::CreateMetadataAccountV3Cpi,
-    state::{DataV2, Creator as MetaplexCreator, Collection as MetaplexCollection},
+    accounts::Metadata,
+    instructions::{
+        CreateMasterEditionV3Cpi, CreateMasterEditionV3CpiAccounts,
+        CreateMasterEditionV3InstructionArgs, CreateMetadataAccountV3Cpi,
+        CreateMetadataAccountV3CpiAccounts, CreateMetadataAccountV3InstructionArgs,
+    },
+    types::{DataV2, Creator as MetaplexCreator, Collection as MetaplexCollection},
 };
+use crate::{calculate_fee, percentage_share, MarketplaceError};
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_metaplex_metadata(
     ctx: Context<CreateMetaplexMetadata>,
     name: String,
@@ -12,7 +21,7 @@ pub fn create_metaplex_metadata(
     seller_fee_basis_points: u16,
     creators: Option<Vec<MetaplexCreator>>,
     collection: Option<MetaplexCollection>,
-    uses: Option<mpl_token_metadata::state::Uses>,
+    uses: Option<mpl_token_metadata::types::Uses>,
 ) -> Result<()> {
     let data = DataV2 {
         name,
@@ -24,22 +33,28 @@ pub fn create_metaplex_metadata(
         uses,
     };
 
+    let mint = ctx.accounts.mint.to_account_info();
+    let mint_authority = ctx.accounts.mint_authority.to_account_info();
+    let payer = ctx.accounts.payer.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+
     // Create metadata account following Metaplex standards
     let create_metadata_ix = CreateMetadataAccountV3Cpi::new(
         &ctx.accounts.token_metadata_program,
         CreateMetadataAccountV3CpiAccounts {
-            metadata: ctx.accounts.metadata.to_account_info(),
-            mint: ctx.accounts.mint.to_account_info(),
-            mint_authority: ctx.accounts.mint_authority.to_account_info(),
-            update_authority: ctx.accounts.update_authority.to_account_info(),
-            payer: ctx.accounts.payer.to_account_info(),
-            system_program: ctx.accounts.system_program.to_account_info(),
-            rent: ctx.accounts.rent.to_account_info(),
+            metadata: &ctx.accounts.metadata,
+            mint: &mint,
+            mint_authority: &mint_authority,
+            payer: &payer,
+            update_authority: (&ctx.accounts.update_authority, true),
+            system_program: &system_program,
+            rent: Some(&ctx.accounts.rent),
+        },
+        CreateMetadataAccountV3InstructionArgs {
+            data,
+            is_mutable: false,
+            collection_details: None,
         },
-        data,
-        false, // is_mutable
-        true,  // update_authority_is_signer
-        None,  // collection_details
     );
 
     create_metadata_ix.invoke()?;
@@ -73,13 +88,264 @@ pub struct CreateMetaplexMetadata<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[allow(clippy::too_many_arguments)]
+pub fn mint_nft(
+    ctx: Context<MintNFT>,
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    creators: Option<Vec<crate::Creator>>,
+    collection: Option<Pubkey>,
+    is_mutable: bool,
+    max_supply: Option<u64>,
+) -> Result<()> {
+    // Mint the single token to the recipient before the edition takes over the mint
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.mint.to_account_info(),
+        to: ctx.accounts.recipient_token_account.to_account_info(),
+        authority: ctx.accounts.authority.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::mint_to(cpi_ctx, 1)?;
+
+    let data = DataV2 {
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        creators: creators.map(|creators| {
+            creators
+                .into_iter()
+                .map(|c| MetaplexCreator {
+                    address: c.address,
+                    verified: c.verified,
+                    share: c.share,
+                })
+                .collect()
+        }),
+        // Left unverified; the collection authority verifies it separately
+        collection: collection.map(|key| MetaplexCollection { verified: false, key }),
+        uses: None,
+    };
+
+    let token_metadata_program = ctx.accounts.token_metadata_program.to_account_info();
+    let metadata = ctx.accounts.metadata.to_account_info();
+    let master_edition = ctx.accounts.master_edition.to_account_info();
+    let mint = ctx.accounts.mint.to_account_info();
+    let authority = ctx.accounts.authority.to_account_info();
+    let payer = ctx.accounts.payer.to_account_info();
+    let token_program = ctx.accounts.token_program.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    let rent = ctx.accounts.rent.to_account_info();
+
+    CreateMetadataAccountV3Cpi::new(
+        &token_metadata_program,
+        CreateMetadataAccountV3CpiAccounts {
+            metadata: &metadata,
+            mint: &mint,
+            mint_authority: &authority,
+            payer: &payer,
+            update_authority: (&authority, true),
+            system_program: &system_program,
+            rent: Some(&rent),
+        },
+        CreateMetadataAccountV3InstructionArgs {
+            data,
+            is_mutable,
+            collection_details: None,
+        },
+    )
+    .invoke()?;
+
+    // The master edition takes over mint and freeze authority, capping supply at 1
+    CreateMasterEditionV3Cpi::new(
+        &token_metadata_program,
+        CreateMasterEditionV3CpiAccounts {
+            edition: &master_edition,
+            mint: &mint,
+            update_authority: &authority,
+            mint_authority: &authority,
+            payer: &payer,
+            metadata: &metadata,
+            token_program: &token_program,
+            system_program: &system_program,
+            rent: Some(&rent),
+        },
+        CreateMasterEditionV3InstructionArgs { max_supply },
+    )
+    .invoke()?;
+
+    msg!("NFT minted with master edition, max supply {:?}", max_supply);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MintNFT<'info> {
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = authority,
+        mint::freeze_authority = authority
+    )]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = recipient
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Owner of the minted NFT
+    pub recipient: AccountInfo<'info>,
+
+    /// Mint and update authority of the new NFT
+    pub authority: Signer<'info>,
+
+    /// CHECK: Metadata PDA, created by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub metadata: AccountInfo<'info>,
+
+    /// CHECK: Master edition PDA, created by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint.key().as_ref(),
+            b"edition"
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub master_edition: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Token Metadata program
+    #[account(address = mpl_token_metadata::ID)]
+    pub token_metadata_program: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
 // Helper function to verify Metaplex metadata
 pub fn verify_metaplex_metadata(
-    metadata: &AccountInfo,
-    expected_mint: &Pubkey,
+    _metadata: &AccountInfo,
+    _expected_mint: &Pubkey,
 ) -> Result<()> {
     // This would deserialize and verify the metadata account
     // matches Metaplex standards
     msg!("Verifying Metaplex metadata standards compliance");
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Computes the royalty owed to each creator in a token's metadata for a
+/// sale of `amount`. Creator accounts must follow in metadata order; one
+/// account is consumed per creator, so the returned length tells callers
+/// how far to advance through their remaining accounts.
+pub fn metadata_creator_payouts<'a, 'info>(
+    metadata: &AccountInfo<'info>,
+    mint: &Pubkey,
+    amount: u64,
+    creator_accounts: &'a [AccountInfo<'info>],
+) -> Result<Vec<(&'a AccountInfo<'info>, u64)>> {
+    let shares = metadata_creator_shares(metadata, mint, amount)?;
+    require!(
+        creator_accounts.len() >= shares.len(),
+        MarketplaceError::CreatorMismatch
+    );
+
+    let mut payouts = Vec::with_capacity(shares.len());
+    for ((creator, share), account) in shares.into_iter().zip(creator_accounts.iter()) {
+        require_keys_eq!(account.key(), creator, MarketplaceError::CreatorMismatch);
+        payouts.push((account, share));
+    }
+    Ok(payouts)
+}
+
+/// Royalty owed to each creator address in a token's metadata for a sale of
+/// `amount`, in whatever unit `amount` is denominated in. A mint that never
+/// had metadata created owes no royalty; the account must still be the
+/// mint's metadata PDA, so royalties cannot be skipped by passing some
+/// other empty account.
+pub fn metadata_creator_shares(
+    metadata: &AccountInfo,
+    mint: &Pubkey,
+    amount: u64,
+) -> Result<Vec<(Pubkey, u64)>> {
+    require_keys_eq!(metadata.key(), Metadata::find_pda(mint).0, MarketplaceError::InvalidMetadata);
+    if metadata.data_is_empty() {
+        return Ok(Vec::new());
+    }
+    require_keys_eq!(*metadata.owner, mpl_token_metadata::ID, MarketplaceError::InvalidMetadata);
+    let metadata = Metadata::try_from(metadata).map_err(|_| MarketplaceError::InvalidMetadata)?;
+    require_keys_eq!(metadata.mint, *mint, MarketplaceError::InvalidMetadata);
+
+    let creators = match metadata.creators {
+        Some(creators) => creators,
+        None => return Ok(Vec::new()),
+    };
+
+    let royalty = calculate_fee(amount, metadata.seller_fee_basis_points)?;
+    let mut shares = Vec::with_capacity(creators.len());
+    for creator in creators.iter() {
+        shares.push((creator.address, percentage_share(royalty, creator.share)?));
+    }
+    Ok(shares)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shares(
+        key: Pubkey,
+        owner: Pubkey,
+        data: &mut [u8],
+        mint: &Pubkey,
+    ) -> Result<Vec<(Pubkey, u64)>> {
+        let mut lamports = 0;
+        let metadata = AccountInfo::new(&key, false, false, &mut lamports, data, &owner, false, 0);
+        metadata_creator_shares(&metadata, mint, 1_000)
+    }
+
+    #[test]
+    fn missing_metadata_owes_no_royalty() {
+        let mint = Pubkey::new_unique();
+        let (pda, _) = Metadata::find_pda(&mint);
+        assert_eq!(shares(pda, System::id(), &mut [], &mint).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn metadata_must_be_the_mint_pda() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(
+            shares(Pubkey::new_unique(), System::id(), &mut [], &mint).unwrap_err(),
+            MarketplaceError::InvalidMetadata.into()
+        );
+    }
+
+    #[test]
+    fn metadata_must_be_owned_by_token_metadata() {
+        let mint = Pubkey::new_unique();
+        let (pda, _) = Metadata::find_pda(&mint);
+        assert_eq!(
+            shares(pda, Pubkey::new_unique(), &mut [4; 8], &mint).unwrap_err(),
+            MarketplaceError::InvalidMetadata.into()
+        );
+    }
+}
@@ -0,0 +1,450 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use crate::metaplex_standards::metadata_creator_payouts;
+use crate::{
+    calculate_fee, keeper_payout, pay_from_buyer, pay_from_escrow, resolve_start_time,
+    seller_proceeds, BidRefund, Marketplace, MarketplaceError,
+};
+
+/// Upper bound on units per multi-unit auction, which also bounds the bid book.
+pub const MAX_AUCTION_UNITS: usize = 20;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct UnitBid {
+    pub bidder: Pubkey,
+    pub amount: u64,
+}
+
+impl UnitBid {
+    pub const LEN: usize = 32 + 8;
+}
+
+/// Uniform-price auction of `quantity` identical units of one mint. The
+/// book keeps the top `quantity` bids sorted from highest to lowest; once
+/// bidding closes every winner pays the clearing price.
+#[account]
+pub struct MultiUnitAuction {
+    pub marketplace: Pubkey,
+    pub seller: Pubkey,
+    pub nft_mint: Pubkey,
+    pub quantity: u64,
+    pub starting_price: u64,
+    pub bids: Vec<UnitBid>,
+    /// Set on the first settlement call
+    pub clearing_price: u64,
+    /// Winners already paid out by the settlement crank
+    pub settled_count: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub is_active: bool,
+    pub bump: u8,
+}
+
+impl MultiUnitAuction {
+    pub const LEN: usize =
+        32 + 32 + 32 + 8 + 8 + (4 + UnitBid::LEN * MAX_AUCTION_UNITS) + 8 + 8 + 8 + 8 + 1 + 1;
+
+    fn book_is_full(&self) -> bool {
+        self.bids.len() as u64 >= self.quantity
+    }
+
+    /// The K-th highest bid, or the starting price when units go unfilled.
+    fn compute_clearing_price(&self) -> u64 {
+        match self.bids.last() {
+            Some(lowest) if self.book_is_full() => lowest.amount,
+            _ => self.starting_price,
+        }
+    }
+}
+
+pub fn create_multi_unit_auction(
+    ctx: Context<CreateMultiUnitAuction>,
+    quantity: u64,
+    starting_price: u64,
+    duration: i64,
+    starts_at: Option<i64>,
+) -> Result<()> {
+    require!(
+        quantity > 0 && quantity <= MAX_AUCTION_UNITS as u64,
+        MarketplaceError::InvalidQuantity
+    );
+    require!(
+        ctx.accounts.seller_nft_account.amount >= quantity,
+        MarketplaceError::InvalidQuantity
+    );
+    require!(starting_price > 0, MarketplaceError::InvalidPrice);
+    require!(duration > 0, MarketplaceError::InvalidDuration);
+
+    let start_time = resolve_start_time(starts_at, Clock::get()?.unix_timestamp)?;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.marketplace = ctx.accounts.marketplace.key();
+    auction.seller = ctx.accounts.seller.key();
+    auction.nft_mint = ctx.accounts.nft_mint.key();
+    auction.quantity = quantity;
+    auction.starting_price = starting_price;
+    auction.bids = Vec::new();
+    auction.clearing_price = 0;
+    auction.settled_count = 0;
+    auction.start_time = start_time;
+    auction.end_time = start_time + duration;
+    auction.is_active = true;
+    auction.bump = ctx.bumps.auction;
+
+    // Escrow all K units
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.seller_nft_account.to_account_info(),
+        to: ctx.accounts.escrow_nft_account.to_account_info(),
+        authority: ctx.accounts.seller.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, quantity)?;
+
+    msg!("Multi-unit auction created for {} units", quantity);
+    Ok(())
+}
+
+/// Places a bid for one unit. When the book is full the lowest bid is
+/// evicted and credited to its bidder's `BidRefund` account.
+pub fn place_unit_bid(ctx: Context<PlaceUnitBid>, bid_amount: u64) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    let clock = Clock::get()?;
+
+    require!(auction.is_active, MarketplaceError::AuctionNotActive);
+    require!(clock.unix_timestamp >= auction.start_time, MarketplaceError::AuctionNotStarted);
+    require!(clock.unix_timestamp < auction.end_time, MarketplaceError::AuctionEnded);
+    require!(bid_amount >= auction.starting_price, MarketplaceError::BidBelowStarting);
+    // Only an eviction needs a refund account; bids into an open book create none
+    require!(
+        auction.book_is_full() == ctx.accounts.evicted_bidder_refund.is_some(),
+        MarketplaceError::InvalidRefundAccount
+    );
+
+    if auction.book_is_full() {
+        let lowest = *auction.bids.last().ok_or(MarketplaceError::InvalidQuantity)?;
+        require!(bid_amount > lowest.amount, MarketplaceError::BidTooLow);
+
+        let auction_info = auction.to_account_info();
+        let bid_refund = ctx
+            .accounts
+            .evicted_bidder_refund
+            .as_mut()
+            .ok_or(MarketplaceError::InvalidRefundAccount)?;
+        bid_refund.bidder = lowest.bidder;
+        bid_refund.auction = auction_info.key();
+        bid_refund.amount = bid_refund.amount.checked_add(lowest.amount).ok_or(MarketplaceError::MathOverflow)?;
+        bid_refund.bump = ctx.bumps.evicted_bidder_refund.ok_or(MarketplaceError::InvalidRefundAccount)?;
+
+        pay_from_escrow(&auction_info, &bid_refund.to_account_info(), lowest.amount)?;
+
+        ctx.accounts.auction.bids.pop();
+    }
+
+    // Escrow new bid
+    pay_from_buyer(
+        &ctx.accounts.bidder,
+        &ctx.accounts.auction.to_account_info(),
+        &ctx.accounts.system_program,
+        bid_amount,
+    )?;
+
+    // Equal bids keep time priority: the new bid goes after them
+    let bids = &mut ctx.accounts.auction.bids;
+    let position = bids.iter().position(|bid| bid.amount < bid_amount).unwrap_or(bids.len());
+    bids.insert(position, UnitBid { bidder: ctx.accounts.bidder.key(), amount: bid_amount });
+
+    msg!("Unit bid placed for {} lamports", bid_amount);
+    Ok(())
+}
+
+/// Settlement crank. Pays out up to `max_winners` winners per call; the call
+/// that processes the last winner returns unsold units and closes the auction.
+///
+/// Remaining accounts: the NFT's creators in metadata order, then a
+/// `[winner, winner token account]` pair for each winner in this chunk.
+pub fn settle_multi_unit_auction<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SettleMultiUnitAuction<'info>>,
+    max_winners: u8,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp >= ctx.accounts.auction.end_time,
+        MarketplaceError::AuctionNotEnded
+    );
+
+    // Bidding is closed, so the clearing price is fixed on the first call
+    if ctx.accounts.auction.is_active {
+        let auction = &mut ctx.accounts.auction;
+        auction.clearing_price = auction.compute_clearing_price();
+        auction.is_active = false;
+    }
+
+    let auction = &ctx.accounts.auction;
+    let clearing_price = auction.clearing_price;
+    let start = auction.settled_count as usize;
+    let end = (start + max_winners as usize).min(auction.bids.len());
+    let winners = auction.bids[start..end].to_vec();
+    let total_bids = auction.bids.len();
+    let unsold = auction
+        .quantity
+        .checked_sub(total_bids as u64)
+        .ok_or(MarketplaceError::MathOverflow)?;
+    let nft_mint = auction.nft_mint;
+    let marketplace_key = auction.marketplace;
+    let seeds = &[
+        b"multi_unit_auction",
+        marketplace_key.as_ref(),
+        nft_mint.as_ref(),
+        &[auction.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let auction_info = ctx.accounts.auction.to_account_info();
+
+    let proceeds = clearing_price
+        .checked_mul(winners.len() as u64)
+        .ok_or(MarketplaceError::MathOverflow)?;
+    let marketplace_fee = calculate_fee(proceeds, ctx.accounts.marketplace.fee_basis_points)?;
+
+    let remaining = ctx.remaining_accounts;
+    let payouts = metadata_creator_payouts(&ctx.accounts.nft_metadata, &nft_mint, proceeds, remaining)?;
+    let mut cursor = payouts.len();
+
+    let mut royalty_paid: u64 = 0;
+    for (creator, amount) in payouts {
+        pay_from_escrow(&auction_info, creator, amount)?;
+        royalty_paid += amount;
+    }
+
+    let mut fronted_rent: u64 = 0;
+    for bid in winners.iter() {
+        require!(remaining.len() >= cursor + 2, MarketplaceError::InvalidAuctionAccounts);
+        let winner = &remaining[cursor];
+        let winner_nft_account = &remaining[cursor + 1];
+        cursor += 2;
+
+        require_keys_eq!(winner.key(), bid.bidder, MarketplaceError::InvalidWinner);
+
+        if winner_nft_account.data_is_empty() {
+            fronted_rent += Rent::get()?.minimum_balance(TokenAccount::LEN);
+        }
+
+        let cpi_accounts = Create {
+            payer: ctx.accounts.keeper.to_account_info(),
+            associated_token: winner_nft_account.clone(),
+            authority: winner.clone(),
+            mint: ctx.accounts.nft_mint.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+        associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_nft_account.to_account_info(),
+            to: winner_nft_account.clone(),
+            authority: auction_info.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        // Winners bid at least the clearing price; the difference goes back
+        let excess = bid.amount - clearing_price;
+        pay_from_escrow(&auction_info, winner, excess)?;
+    }
+
+    // The tip is on this chunk's proceeds, so every crank call is paid
+    let keeper_amount = keeper_payout(
+        proceeds,
+        ctx.accounts.marketplace.keeper_tip_basis_points,
+        fronted_rent,
+        &[marketplace_fee, royalty_paid],
+    )?;
+    let seller_amount = seller_proceeds(proceeds, &[marketplace_fee, royalty_paid, keeper_amount])?;
+
+    pay_from_escrow(&auction_info, &ctx.accounts.treasury.to_account_info(), marketplace_fee)?;
+
+    pay_from_escrow(&auction_info, &ctx.accounts.keeper.to_account_info(), keeper_amount)?;
+
+    pay_from_escrow(&auction_info, &ctx.accounts.seller.to_account_info(), seller_amount)?;
+
+    let marketplace = &mut ctx.accounts.marketplace;
+    marketplace.total_sales += winners.len() as u64;
+    marketplace.total_volume += proceeds;
+
+    ctx.accounts.auction.settled_count = end as u64;
+
+    if end == total_bids {
+        // Return units nobody bid on
+        if unsold > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.escrow_nft_account.to_account_info(),
+                to: ctx.accounts.seller_nft_account.to_account_info(),
+                authority: auction_info.clone(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, unsold)?;
+        }
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.escrow_nft_account.to_account_info(),
+            destination: ctx.accounts.seller.to_account_info(),
+            authority: auction_info.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+
+        ctx.accounts.auction.close(ctx.accounts.seller.to_account_info())?;
+
+        msg!("Multi-unit auction settled at {} lamports per unit", clearing_price);
+    } else {
+        msg!("Settled {} of {} winners", end, total_bids);
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CreateMultiUnitAuction<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + MultiUnitAuction::LEN,
+        seeds = [b"multi_unit_auction", marketplace.key().as_ref(), nft_mint.key().as_ref()],
+        bump
+    )]
+    pub auction: Account<'info, MultiUnitAuction>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub nft_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == nft_mint.key(),
+        constraint = seller_nft_account.owner == seller.key()
+    )]
+    pub seller_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = nft_mint,
+        associated_token::authority = auction
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceUnitBid<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"multi_unit_auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub auction: Account<'info, MultiUnitAuction>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    /// CHECK: Lowest bidder when the book is full, otherwise the bidder itself
+    #[account(
+        constraint = !auction.book_is_full()
+            || auction.bids.last().map(|bid| bid.bidder) == Some(evicted_bidder.key())
+            @ MarketplaceError::InvalidWinner
+    )]
+    pub evicted_bidder: AccountInfo<'info>,
+
+    /// Refund account of the evicted bidder; omitted while the book has room
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        space = 8 + BidRefund::LEN,
+        seeds = [b"bid_refund", auction.key().as_ref(), evicted_bidder.key().as_ref()],
+        bump
+    )]
+    pub evicted_bidder_refund: Option<Account<'info, BidRefund>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleMultiUnitAuction<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"multi_unit_auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub auction: Account<'info, MultiUnitAuction>,
+
+    #[account(mut, constraint = seller.key() == auction.seller)]
+    pub seller: SystemAccount<'info>,
+
+    /// Anyone may crank settlement; fronts missing winner token accounts and
+    /// is reimbursed plus the keeper tip on each chunk
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(address = auction.nft_mint)]
+    pub nft_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = auction
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == auction.nft_mint,
+        constraint = seller_nft_account.owner == seller.key()
+    )]
+    pub seller_nft_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Metadata PDA of the auctioned mint
+    #[account(
+        seeds = [b"metadata", mpl_token_metadata::ID.as_ref(), auction.nft_mint.as_ref()],
+        bump,
+        seeds::program = mpl_token_metadata::ID
+    )]
+    pub nft_metadata: AccountInfo<'info>,
+
+    /// CHECK: Treasury account
+    #[account(mut, constraint = treasury.key() == marketplace.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,359 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use crate::{
+    calculate_fee, pay_from_buyer, pay_from_escrow, seller_proceeds, Marketplace, MarketplaceError,
+};
+
+/// Peer-to-peer NFT-for-NFT trade. The proposer's NFT and SOL top-up are
+/// escrowed under this PDA until the counterparty accepts or the proposer
+/// cancels.
+#[account]
+pub struct Swap {
+    pub marketplace: Pubkey,
+    pub proposer: Pubkey,
+    pub counterparty: Option<Pubkey>,
+    pub offered_mint: Pubkey,
+    pub requested_mint: Pubkey,
+    /// SOL escrowed by the proposer and paid to the counterparty
+    pub proposer_lamports: u64,
+    /// SOL the counterparty pays to the proposer on acceptance
+    pub counterparty_lamports: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Swap {
+    pub const LEN: usize = 32 + 32 + (1 + 32) + 32 + 32 + 8 + 8 + 8 + 1;
+}
+
+pub fn propose_swap(
+    ctx: Context<ProposeSwap>,
+    requested_mint: Pubkey,
+    proposer_lamports: u64,
+    counterparty_lamports: u64,
+    counterparty: Option<Pubkey>,
+) -> Result<()> {
+    require!(
+        proposer_lamports == 0 || counterparty_lamports == 0,
+        MarketplaceError::InvalidSwapTerms
+    );
+    require_keys_neq!(
+        requested_mint,
+        ctx.accounts.offered_mint.key(),
+        MarketplaceError::InvalidSwapTerms
+    );
+
+    let swap = &mut ctx.accounts.swap;
+    swap.marketplace = ctx.accounts.marketplace.key();
+    swap.proposer = ctx.accounts.proposer.key();
+    swap.counterparty = counterparty;
+    swap.offered_mint = ctx.accounts.offered_mint.key();
+    swap.requested_mint = requested_mint;
+    swap.proposer_lamports = proposer_lamports;
+    swap.counterparty_lamports = counterparty_lamports;
+    swap.created_at = Clock::get()?.unix_timestamp;
+    swap.bump = ctx.bumps.swap;
+
+    // Transfer offered NFT to escrow
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.proposer_nft_account.to_account_info(),
+        to: ctx.accounts.escrow_nft_account.to_account_info(),
+        authority: ctx.accounts.proposer.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, 1)?;
+
+    // Escrow the SOL top-up alongside the NFT
+    pay_from_buyer(
+        &ctx.accounts.proposer,
+        &ctx.accounts.swap.to_account_info(),
+        &ctx.accounts.system_program,
+        proposer_lamports,
+    )?;
+
+    msg!("Swap proposed: {} for {}", ctx.accounts.offered_mint.key(), requested_mint);
+    Ok(())
+}
+
+pub fn accept_swap(ctx: Context<AcceptSwap>) -> Result<()> {
+    let swap = &ctx.accounts.swap;
+    if let Some(counterparty) = swap.counterparty {
+        require_keys_eq!(
+            counterparty,
+            ctx.accounts.counterparty.key(),
+            MarketplaceError::UnauthorizedCounterparty
+        );
+    }
+
+    let fee_basis_points = ctx.accounts.marketplace.fee_basis_points;
+    let offered_mint = swap.offered_mint;
+    let proposer_lamports = swap.proposer_lamports;
+    let counterparty_lamports = swap.counterparty_lamports;
+    let seeds = &[
+        b"swap",
+        swap.marketplace.as_ref(),
+        offered_mint.as_ref(),
+        &[swap.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Counterparty's NFT goes straight to the proposer
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.counterparty_nft_account.to_account_info(),
+        to: ctx.accounts.proposer_receive_account.to_account_info(),
+        authority: ctx.accounts.counterparty.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, 1)?;
+
+    // Escrowed NFT is released to the counterparty
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.escrow_nft_account.to_account_info(),
+        to: ctx.accounts.counterparty_receive_account.to_account_info(),
+        authority: ctx.accounts.swap.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, 1)?;
+
+    // The marketplace fee only applies to the SOL component
+    let sol_amount = proposer_lamports + counterparty_lamports;
+    let marketplace_fee = calculate_fee(sol_amount, fee_basis_points)?;
+
+    if proposer_lamports > 0 {
+        let counterparty_amount = seller_proceeds(proposer_lamports, &[marketplace_fee])?;
+
+        pay_from_escrow(
+            &ctx.accounts.swap.to_account_info(),
+            &ctx.accounts.treasury.to_account_info(),
+            marketplace_fee,
+        )?;
+
+        pay_from_escrow(
+            &ctx.accounts.swap.to_account_info(),
+            &ctx.accounts.counterparty.to_account_info(),
+            counterparty_amount,
+        )?;
+    } else if counterparty_lamports > 0 {
+        let proposer_amount = seller_proceeds(counterparty_lamports, &[marketplace_fee])?;
+
+        pay_from_buyer(
+            &ctx.accounts.counterparty,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+            marketplace_fee,
+        )?;
+        pay_from_buyer(
+            &ctx.accounts.counterparty,
+            &ctx.accounts.proposer.to_account_info(),
+            &ctx.accounts.system_program,
+            proposer_amount,
+        )?;
+    }
+
+    // Close the emptied escrow, returning rent to the proposer
+    let cpi_accounts = CloseAccount {
+        account: ctx.accounts.escrow_nft_account.to_account_info(),
+        destination: ctx.accounts.proposer.to_account_info(),
+        authority: ctx.accounts.swap.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::close_account(cpi_ctx)?;
+
+    let marketplace = &mut ctx.accounts.marketplace;
+    marketplace.total_sales += 1;
+    marketplace.total_volume += sol_amount;
+
+    msg!("Swap executed with {} lamports SOL component", sol_amount);
+    Ok(())
+}
+
+pub fn cancel_swap(ctx: Context<CancelSwap>) -> Result<()> {
+    let swap = &ctx.accounts.swap;
+    let offered_mint = swap.offered_mint;
+    let seeds = &[
+        b"swap",
+        swap.marketplace.as_ref(),
+        offered_mint.as_ref(),
+        &[swap.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Return NFT to proposer
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.escrow_nft_account.to_account_info(),
+        to: ctx.accounts.proposer_nft_account.to_account_info(),
+        authority: ctx.accounts.swap.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, 1)?;
+
+    let cpi_accounts = CloseAccount {
+        account: ctx.accounts.escrow_nft_account.to_account_info(),
+        destination: ctx.accounts.proposer.to_account_info(),
+        authority: ctx.accounts.swap.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::close_account(cpi_ctx)?;
+
+    // Escrowed SOL is returned when the swap account closes
+    msg!("Swap cancelled");
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ProposeSwap<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + Swap::LEN,
+        seeds = [b"swap", marketplace.key().as_ref(), offered_mint.key().as_ref()],
+        bump
+    )]
+    pub swap: Account<'info, Swap>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub offered_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = proposer_nft_account.mint == offered_mint.key(),
+        constraint = proposer_nft_account.owner == proposer.key(),
+        constraint = proposer_nft_account.amount == 1
+    )]
+    pub proposer_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = proposer,
+        associated_token::mint = offered_mint,
+        associated_token::authority = swap
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptSwap<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"swap", marketplace.key().as_ref(), swap.offered_mint.as_ref()],
+        bump = swap.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        close = proposer
+    )]
+    pub swap: Account<'info, Swap>,
+
+    #[account(mut)]
+    pub counterparty: Signer<'info>,
+
+    #[account(mut, constraint = proposer.key() == swap.proposer)]
+    pub proposer: SystemAccount<'info>,
+
+    #[account(address = swap.offered_mint)]
+    pub offered_mint: Account<'info, Mint>,
+
+    #[account(address = swap.requested_mint)]
+    pub requested_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = escrow_nft_account.mint == swap.offered_mint,
+        constraint = escrow_nft_account.amount == 1
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = counterparty_nft_account.mint == swap.requested_mint,
+        constraint = counterparty_nft_account.owner == counterparty.key(),
+        constraint = counterparty_nft_account.amount == 1
+    )]
+    pub counterparty_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = counterparty,
+        associated_token::mint = offered_mint,
+        associated_token::authority = counterparty
+    )]
+    pub counterparty_receive_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = counterparty,
+        associated_token::mint = requested_mint,
+        associated_token::authority = proposer
+    )]
+    pub proposer_receive_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Treasury account
+    #[account(mut, constraint = treasury.key() == marketplace.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSwap<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"swap", marketplace.key().as_ref(), swap.offered_mint.as_ref()],
+        bump = swap.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = swap.proposer == proposer.key(),
+        close = proposer
+    )]
+    pub swap: Account<'info, Swap>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_nft_account.mint == swap.offered_mint,
+        constraint = escrow_nft_account.amount == 1
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = proposer_nft_account.mint == swap.offered_mint,
+        constraint = proposer_nft_account.owner == proposer.key()
+    )]
+    pub proposer_nft_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
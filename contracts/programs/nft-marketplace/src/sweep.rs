@@ -0,0 +1,217 @@
+use anchor_lang::prelude::*;
+use solana_program::compute_units::sol_remaining_compute_units;
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
+use anchor_spl::token::{self, CloseAccount, Token, TokenAccount, Transfer};
+use crate::{
+    pay_from_buyer, split_sale, AssetStandard, Listing, Marketplace, MarketplaceError, SaleSplit,
+};
+
+/// Accounts per listing passed to `buy_many`: listing, NFT mint, escrow
+/// token account, buyer token account, seller, royalty recipient.
+pub const SWEEP_ITEM_ACCOUNTS: usize = 6;
+
+/// Compute budget kept in reserve for each purchase; the sweep stops once
+/// less than this remains so the aggregated payouts can still run.
+const COMPUTE_UNITS_PER_PURCHASE: u64 = 40_000;
+
+/// Buys one unit from each listing passed in the remaining accounts, as
+/// long as `max_spend` and the compute budget allow. A listing that cannot
+/// be bought either ends the sweep or, with `skip_failures`, is passed over.
+/// Fees, royalties and seller proceeds are summed per recipient and paid
+/// once at the end.
+pub fn buy_many<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BuyMany<'info>>,
+    max_spend: u64,
+    skip_failures: bool,
+) -> Result<()> {
+    let remaining = ctx.remaining_accounts;
+    require!(
+        !remaining.is_empty() && remaining.len() % SWEEP_ITEM_ACCOUNTS == 0,
+        MarketplaceError::InvalidSweepAccounts
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    let marketplace_key = ctx.accounts.marketplace.key();
+    let buyer_key = ctx.accounts.buyer.key();
+    let fee_basis_points = ctx.accounts.marketplace.fee_basis_points;
+
+    let mut spent: u64 = 0;
+    let mut bought: u64 = 0;
+    let mut marketplace_fees: u64 = 0;
+    let mut royalties: Vec<(AccountInfo<'info>, u64)> = Vec::new();
+    let mut proceeds: Vec<(AccountInfo<'info>, u64)> = Vec::new();
+
+    for item in remaining.chunks(SWEEP_ITEM_ACCOUNTS) {
+        if sol_remaining_compute_units() < COMPUTE_UNITS_PER_PURCHASE {
+            msg!("Compute budget exhausted, stopping sweep");
+            break;
+        }
+
+        let listing_info = &item[0];
+        let nft_mint = &item[1];
+        let escrow_nft_account = &item[2];
+        let buyer_nft_account = &item[3];
+        let seller = &item[4];
+        let royalty_recipient = &item[5];
+
+        let mut listing = match load_sweep_listing(item, marketplace_key, buyer_key, now, max_spend - spent) {
+            Ok(listing) => listing,
+            Err(err) => {
+                msg!("Listing {} not bought: {}", listing_info.key(), err);
+                if skip_failures {
+                    continue;
+                }
+                break;
+            }
+        };
+
+        let price = listing.price;
+        let SaleSplit {
+            marketplace_fee,
+            royalty: royalty_fee,
+            seller_amount,
+        } = split_sale(price, fee_basis_points, listing.royalty_percentage)?;
+
+        let cpi_accounts = Create {
+            payer: ctx.accounts.buyer.to_account_info(),
+            associated_token: buyer_nft_account.clone(),
+            authority: ctx.accounts.buyer.to_account_info(),
+            mint: nft_mint.clone(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+        associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+
+        let mint_key = listing.nft_mint;
+        let seeds = &[
+            b"listing",
+            marketplace_key.as_ref(),
+            mint_key.as_ref(),
+            &[listing.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: escrow_nft_account.clone(),
+            to: buyer_nft_account.clone(),
+            authority: listing_info.clone(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        if listing.quantity == 1 {
+            // Close the emptied escrow and the listing, as in buy_nft
+            let cpi_accounts = CloseAccount {
+                account: escrow_nft_account.clone(),
+                destination: seller.clone(),
+                authority: listing_info.clone(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::close_account(cpi_ctx)?;
+
+            listing.close(seller.clone())?;
+        } else {
+            listing.quantity -= 1;
+            listing.exit(&crate::ID)?;
+        }
+
+        marketplace_fees += marketplace_fee;
+        credit(&mut royalties, royalty_recipient, royalty_fee);
+        credit(&mut proceeds, seller, seller_amount);
+        spent += price;
+        bought += 1;
+    }
+
+    // One transfer per recipient, however many listings they were part of
+    pay_from_buyer(
+        &ctx.accounts.buyer,
+        &ctx.accounts.treasury,
+        &ctx.accounts.system_program,
+        marketplace_fees,
+    )?;
+    for (recipient, amount) in royalties.iter().chain(proceeds.iter()) {
+        pay_from_buyer(&ctx.accounts.buyer, recipient, &ctx.accounts.system_program, *amount)?;
+    }
+
+    let marketplace = &mut ctx.accounts.marketplace;
+    marketplace.total_sales += bought;
+    marketplace.total_volume += spent;
+
+    msg!("Swept {} listing(s) for {} lamports", bought, spent);
+    Ok(())
+}
+
+/// Loads a listing and checks everything that can make it unbuyable before
+/// any state changes, so a failure here can be skipped safely.
+fn load_sweep_listing<'info>(
+    item: &'info [AccountInfo<'info>],
+    marketplace: Pubkey,
+    buyer: Pubkey,
+    now: i64,
+    budget: u64,
+) -> Result<Account<'info, Listing>> {
+    let listing = Account::<Listing>::try_from(&item[0])?;
+    require_keys_eq!(listing.marketplace, marketplace, MarketplaceError::MarketplaceMismatch);
+    require!(listing.is_active, MarketplaceError::ListingNotActive);
+    require!(
+        listing.asset_standard == AssetStandard::Legacy,
+        MarketplaceError::InvalidTokenStandard
+    );
+    require!(now >= listing.starts_at, MarketplaceError::ListingNotStarted);
+    if let Some(allowed_buyer) = listing.allowed_buyer {
+        require_keys_eq!(allowed_buyer, buyer, MarketplaceError::BuyerNotAllowed);
+    }
+    require!(listing.price <= budget, MarketplaceError::SweepBudgetExceeded);
+
+    require_keys_eq!(item[1].key(), listing.nft_mint, MarketplaceError::InvalidSweepAccounts);
+    let escrow_nft_account = Account::<TokenAccount>::try_from(&item[2])?;
+    require_keys_eq!(escrow_nft_account.mint, listing.nft_mint, MarketplaceError::InvalidSweepAccounts);
+    require_keys_eq!(escrow_nft_account.owner, listing.key(), MarketplaceError::InvalidSweepAccounts);
+    require!(escrow_nft_account.amount >= 1, MarketplaceError::InvalidSweepAccounts);
+    require_keys_eq!(
+        item[3].key(),
+        associated_token::get_associated_token_address(&buyer, &listing.nft_mint),
+        MarketplaceError::InvalidSweepAccounts
+    );
+    require_keys_eq!(item[4].key(), listing.seller, MarketplaceError::InvalidSweepAccounts);
+    require_keys_eq!(
+        item[5].key(),
+        listing.royalty_recipient,
+        MarketplaceError::InvalidSweepAccounts
+    );
+    Ok(listing)
+}
+
+fn credit<'info>(payouts: &mut Vec<(AccountInfo<'info>, u64)>, recipient: &AccountInfo<'info>, amount: u64) {
+    if amount == 0 {
+        return;
+    }
+    match payouts.iter_mut().find(|(account, _)| account.key() == recipient.key()) {
+        Some((_, total)) => *total += amount,
+        None => payouts.push((recipient.clone(), amount)),
+    }
+}
+
+#[derive(Accounts)]
+pub struct BuyMany<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: Treasury account
+    #[account(mut, constraint = treasury.key() == marketplace.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
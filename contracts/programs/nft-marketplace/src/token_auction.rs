@@ -0,0 +1,771 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use crate::{
+    calculate_fee, keeper_payout, metadata_creator_shares, pay_from_escrow, resolve_start_time,
+    seller_proceeds, Marketplace, MarketplaceError,
+};
+
+/// English auction priced in an SPL mint such as USDC. Bids are held in a
+/// token vault owned by this PDA rather than as lamports on the account.
+#[account]
+pub struct TokenAuction {
+    pub marketplace: Pubkey,
+    pub seller: Pubkey,
+    pub nft_mint: Pubkey,
+    pub payment_mint: Pubkey,
+    /// Prices below are in base units of `payment_mint`
+    pub starting_price: u64,
+    pub current_bid: u64,
+    pub reserve_price: u64,
+    pub highest_bidder: Option<Pubkey>,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub is_active: bool,
+    pub bump: u8,
+}
+
+impl TokenAuction {
+    pub const LEN: usize = 32 + 32 + 32 + 32 + 8 + 8 + 8 + (1 + 32) + 8 + 8 + 1 + 1;
+}
+
+/// Outbid tokens owed to a bidder on a token auction. The tokens sit in an
+/// ATA owned by this PDA, so the bidder can withdraw them whether or not
+/// the auction has been settled since.
+#[account]
+pub struct TokenBidRefund {
+    pub bidder: Pubkey,
+    pub auction: Pubkey,
+    pub payment_mint: Pubkey,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl TokenBidRefund {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 1;
+}
+
+pub fn create_token_auction(
+    ctx: Context<CreateTokenAuction>,
+    starting_price: u64,
+    reserve_price: u64,
+    duration: i64,
+    starts_at: Option<i64>,
+) -> Result<()> {
+    require!(starting_price > 0, MarketplaceError::InvalidPrice);
+    require!(reserve_price >= starting_price, MarketplaceError::InvalidReservePrice);
+    require!(duration > 0, MarketplaceError::InvalidDuration);
+    require_keys_neq!(
+        ctx.accounts.payment_mint.key(),
+        ctx.accounts.nft_mint.key(),
+        MarketplaceError::InvalidPaymentMint
+    );
+
+    let start_time = resolve_start_time(starts_at, Clock::get()?.unix_timestamp)?;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.marketplace = ctx.accounts.marketplace.key();
+    auction.seller = ctx.accounts.seller.key();
+    auction.nft_mint = ctx.accounts.nft_mint.key();
+    auction.payment_mint = ctx.accounts.payment_mint.key();
+    auction.starting_price = starting_price;
+    auction.current_bid = 0;
+    auction.reserve_price = reserve_price;
+    auction.highest_bidder = None;
+    auction.start_time = start_time;
+    auction.end_time = start_time + duration;
+    auction.is_active = true;
+    auction.bump = ctx.bumps.auction;
+
+    // Transfer NFT to escrow
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.seller_nft_account.to_account_info(),
+        to: ctx.accounts.escrow_nft_account.to_account_info(),
+        authority: ctx.accounts.seller.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, 1)?;
+
+    msg!(
+        "Token auction created with starting price {} of mint {}",
+        starting_price,
+        ctx.accounts.payment_mint.key()
+    );
+    Ok(())
+}
+
+pub fn place_token_bid(ctx: Context<PlaceTokenBid>, bid_amount: u64) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    let clock = Clock::get()?;
+
+    require!(auction.is_active, MarketplaceError::AuctionNotActive);
+    require!(clock.unix_timestamp >= auction.start_time, MarketplaceError::AuctionNotStarted);
+    require!(clock.unix_timestamp < auction.end_time, MarketplaceError::AuctionEnded);
+    require!(bid_amount > auction.current_bid, MarketplaceError::BidTooLow);
+    require!(bid_amount >= auction.starting_price, MarketplaceError::BidBelowStarting);
+
+    // Move the outbid amount into the previous bidder's refund vault; they
+    // withdraw it themselves, so a frozen or closed account of theirs cannot
+    // block new bids. The first bid outbids nobody and creates neither.
+    require!(
+        auction.highest_bidder.is_some() == ctx.accounts.previous_bidder_refund.is_some()
+            && auction.highest_bidder.is_some() == ctx.accounts.previous_bidder_refund_vault.is_some(),
+        MarketplaceError::InvalidRefundAccount
+    );
+    if let Some(previous_bidder) = auction.highest_bidder {
+        let refund = auction.current_bid;
+        let auction_key = auction.key();
+        let payment_mint = auction.payment_mint;
+        let seeds = &[
+            b"token_auction",
+            auction.marketplace.as_ref(),
+            auction.nft_mint.as_ref(),
+            &[auction.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let refund_vault = ctx
+            .accounts
+            .previous_bidder_refund_vault
+            .as_ref()
+            .ok_or(MarketplaceError::InvalidRefundAccount)?;
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.bid_vault.to_account_info(),
+            to: refund_vault.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, refund)?;
+
+        let bid_refund = ctx
+            .accounts
+            .previous_bidder_refund
+            .as_mut()
+            .ok_or(MarketplaceError::InvalidRefundAccount)?;
+        bid_refund.bidder = previous_bidder;
+        bid_refund.auction = auction_key;
+        bid_refund.payment_mint = payment_mint;
+        bid_refund.amount = bid_refund.amount.checked_add(refund).ok_or(MarketplaceError::MathOverflow)?;
+        bid_refund.bump = ctx.bumps.previous_bidder_refund.ok_or(MarketplaceError::InvalidRefundAccount)?;
+    }
+
+    // Escrow new bid
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.bidder_token_account.to_account_info(),
+        to: ctx.accounts.bid_vault.to_account_info(),
+        authority: ctx.accounts.bidder.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, bid_amount)?;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.current_bid = bid_amount;
+    auction.highest_bidder = Some(ctx.accounts.bidder.key());
+
+    msg!("Token bid placed for {}", bid_amount);
+    Ok(())
+}
+
+pub fn settle_token_auction<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SettleTokenAuction<'info>>,
+) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    let clock = Clock::get()?;
+
+    require!(auction.is_active, MarketplaceError::AuctionNotActive);
+    require!(clock.unix_timestamp >= auction.end_time, MarketplaceError::AuctionNotEnded);
+
+    let price = auction.current_bid;
+    let nft_mint = auction.nft_mint;
+    let payment_mint = auction.payment_mint;
+    let has_bid = auction.highest_bidder.is_some();
+    let sold = has_bid && price >= auction.reserve_price;
+    let seeds = &[
+        b"token_auction",
+        auction.marketplace.as_ref(),
+        nft_mint.as_ref(),
+        &[auction.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Rent the keeper fronts for token accounts the outcome needs
+    let mut fronted_rent: u64 = 0;
+
+    if sold {
+        fronted_rent += create_payout_account(
+            &ctx,
+            &ctx.accounts.winner_nft_account,
+            &ctx.accounts.winner,
+            &ctx.accounts.nft_mint.to_account_info(),
+        )?;
+        fronted_rent += create_payout_account(
+            &ctx,
+            &ctx.accounts.seller_payment_account,
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.payment_mint.to_account_info(),
+        )?;
+
+        let marketplace_fee = calculate_fee(price, ctx.accounts.marketplace.fee_basis_points)?;
+
+        // Royalties are paid in the payment mint; each creator's token
+        // account follows as a remaining account in metadata order
+        let shares = metadata_creator_shares(&ctx.accounts.nft_metadata, &nft_mint, price)?;
+        require!(
+            ctx.remaining_accounts.len() >= shares.len(),
+            MarketplaceError::CreatorMismatch
+        );
+
+        let mut royalty_paid: u64 = 0;
+        for ((creator, amount), account) in shares.into_iter().zip(ctx.remaining_accounts.iter()) {
+            let creator_token_account = Account::<TokenAccount>::try_from(account)?;
+            require_keys_eq!(creator_token_account.owner, creator, MarketplaceError::CreatorMismatch);
+            require_keys_eq!(
+                creator_token_account.mint,
+                payment_mint,
+                MarketplaceError::InvalidPaymentMint
+            );
+            transfer_from_vault(&ctx, account.clone(), signer, amount)?;
+            royalty_paid += amount;
+        }
+
+        // The tip is paid in the payment mint; the fronted rent is repaid in
+        // lamports once the vaults are closed below
+        let keeper_amount = keeper_payout(
+            price,
+            ctx.accounts.marketplace.keeper_tip_basis_points,
+            0,
+            &[marketplace_fee, royalty_paid],
+        )?;
+        let seller_amount = seller_proceeds(price, &[marketplace_fee, royalty_paid, keeper_amount])?;
+
+        transfer_from_vault(
+            &ctx,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            signer,
+            marketplace_fee,
+        )?;
+        transfer_from_vault(
+            &ctx,
+            ctx.accounts.keeper_payment_account.to_account_info(),
+            signer,
+            keeper_amount,
+        )?;
+        transfer_from_vault(
+            &ctx,
+            ctx.accounts.seller_payment_account.to_account_info(),
+            signer,
+            seller_amount,
+        )?;
+
+        // Transfer NFT to winner
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_nft_account.to_account_info(),
+            to: ctx.accounts.winner_nft_account.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        // Volume is tracked in lamports, so token sales only count towards sales
+        ctx.accounts.marketplace.total_sales += 1;
+
+        msg!("Token auction settled - NFT sold for {} of mint {}", price, payment_mint);
+    } else {
+        // Refund highest bidder
+        if has_bid {
+            fronted_rent += create_payout_account(
+                &ctx,
+                &ctx.accounts.winner_payment_account,
+                &ctx.accounts.winner,
+                &ctx.accounts.payment_mint.to_account_info(),
+            )?;
+            transfer_from_vault(
+                &ctx,
+                ctx.accounts.winner_payment_account.to_account_info(),
+                signer,
+                price,
+            )?;
+        }
+
+        // Return NFT to seller if reserve not met
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_nft_account.to_account_info(),
+            to: ctx.accounts.seller_nft_account.to_account_info(),
+            authority: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+
+        msg!("Token auction ended - reserve price not met");
+    }
+
+    // The vault rent comes back to the auction first so the keeper's rent is
+    // repaid out of it; the rest reaches the seller when the auction closes
+    let auction_info = ctx.accounts.auction.to_account_info();
+    close_auction_vaults(
+        &ctx.accounts.token_program,
+        &auction_info,
+        &auction_info,
+        &[
+            ctx.accounts.escrow_nft_account.to_account_info(),
+            ctx.accounts.bid_vault.to_account_info(),
+        ],
+        signer,
+    )?;
+    pay_from_escrow(&auction_info, &ctx.accounts.keeper.to_account_info(), fronted_rent)
+}
+
+pub fn cancel_token_auction(ctx: Context<CancelTokenAuction>) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    require!(auction.highest_bidder.is_none(), MarketplaceError::AuctionHasBids);
+
+    let seeds = &[
+        b"token_auction",
+        auction.marketplace.as_ref(),
+        auction.nft_mint.as_ref(),
+        &[auction.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Return NFT to seller
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.escrow_nft_account.to_account_info(),
+        to: ctx.accounts.seller_nft_account.to_account_info(),
+        authority: ctx.accounts.auction.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, 1)?;
+
+    close_auction_vaults(
+        &ctx.accounts.token_program,
+        &ctx.accounts.auction.to_account_info(),
+        &ctx.accounts.seller.to_account_info(),
+        &[
+            ctx.accounts.escrow_nft_account.to_account_info(),
+            ctx.accounts.bid_vault.to_account_info(),
+        ],
+        signer,
+    )?;
+
+    msg!("Token auction cancelled");
+    Ok(())
+}
+
+/// Pays out everything credited to the signer's refund vault and closes the
+/// vault and refund account back to them.
+pub fn withdraw_token_bid_refund(ctx: Context<WithdrawTokenBidRefund>) -> Result<()> {
+    let bid_refund = &ctx.accounts.bid_refund;
+    let amount = ctx.accounts.refund_vault.amount;
+    let seeds = &[
+        b"token_bid_refund",
+        bid_refund.auction.as_ref(),
+        bid_refund.bidder.as_ref(),
+        &[bid_refund.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.refund_vault.to_account_info(),
+        to: ctx.accounts.bidder_token_account.to_account_info(),
+        authority: ctx.accounts.bid_refund.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)?;
+
+    let cpi_accounts = CloseAccount {
+        account: ctx.accounts.refund_vault.to_account_info(),
+        destination: ctx.accounts.bidder.to_account_info(),
+        authority: ctx.accounts.bid_refund.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::close_account(cpi_ctx)?;
+
+    msg!("Withdrew {} of mint {} in outbid refunds", amount, bid_refund.payment_mint);
+    Ok(())
+}
+
+fn transfer_from_vault<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, SettleTokenAuction<'info>>,
+    to: AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.bid_vault.to_account_info(),
+        to,
+        authority: ctx.accounts.auction.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)
+}
+
+/// Creates `owner`'s associated token account for `mint` at the keeper's
+/// expense if it is missing, returning the rent the keeper fronted.
+fn create_payout_account<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, SettleTokenAuction<'info>>,
+    account: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+) -> Result<u64> {
+    let fronted_rent = if account.data_is_empty() {
+        Rent::get()?.minimum_balance(TokenAccount::LEN)
+    } else {
+        0
+    };
+    let cpi_accounts = Create {
+        payer: ctx.accounts.keeper.to_account_info(),
+        associated_token: account.clone(),
+        authority: owner.clone(),
+        mint: mint.clone(),
+        system_program: ctx.accounts.system_program.to_account_info(),
+        token_program: ctx.accounts.token_program.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+    associated_token::create_idempotent(CpiContext::new(cpi_program, cpi_accounts))?;
+    Ok(fronted_rent)
+}
+
+/// Closes the emptied NFT escrow and bid vault, returning rent to `destination`.
+fn close_auction_vaults<'info>(
+    token_program: &Program<'info, Token>,
+    auction: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    vaults: &[AccountInfo<'info>],
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    for vault in vaults {
+        let cpi_accounts = CloseAccount {
+            account: vault.clone(),
+            destination: destination.clone(),
+            authority: auction.clone(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CreateTokenAuction<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + TokenAuction::LEN,
+        seeds = [b"token_auction", marketplace.key().as_ref(), nft_mint.key().as_ref()],
+        bump
+    )]
+    pub auction: Account<'info, TokenAuction>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub nft_mint: Account<'info, Mint>,
+
+    /// Mint bids are denominated in
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == nft_mint.key(),
+        constraint = seller_nft_account.owner == seller.key(),
+        constraint = seller_nft_account.amount == 1
+    )]
+    pub seller_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = nft_mint,
+        associated_token::authority = auction
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = payment_mint,
+        associated_token::authority = auction
+    )]
+    pub bid_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceTokenBid<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"token_auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch
+    )]
+    pub auction: Account<'info, TokenAuction>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    #[account(address = auction.payment_mint)]
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = bidder_token_account.mint == auction.payment_mint,
+        constraint = bidder_token_account.owner == bidder.key()
+    )]
+    pub bidder_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Current highest bidder, or the bidder itself on the first bid
+    #[account(
+        constraint = auction.highest_bidder.is_none_or(|bidder| bidder == previous_bidder.key())
+            @ MarketplaceError::InvalidWinner
+    )]
+    pub previous_bidder: AccountInfo<'info>,
+
+    /// Refund account of the outbid bidder; omitted on the first bid
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        space = 8 + TokenBidRefund::LEN,
+        seeds = [b"token_bid_refund", auction.key().as_ref(), previous_bidder.key().as_ref()],
+        bump
+    )]
+    pub previous_bidder_refund: Option<Account<'info, TokenBidRefund>>,
+
+    /// Token vault of the refund account; omitted on the first bid
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        associated_token::mint = payment_mint,
+        associated_token::authority = previous_bidder_refund
+    )]
+    pub previous_bidder_refund_vault: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = payment_mint,
+        associated_token::authority = auction
+    )]
+    pub bid_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleTokenAuction<'info> {
+    #[account(
+        mut,
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"token_auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        close = seller
+    )]
+    pub auction: Account<'info, TokenAuction>,
+
+    #[account(mut, constraint = seller.key() == auction.seller)]
+    pub seller: SystemAccount<'info>,
+
+    /// CHECK: Winner (highest bidder), checked against the auction
+    #[account(
+        constraint = auction.highest_bidder.is_none_or(|bidder| bidder == winner.key())
+            @ MarketplaceError::InvalidWinner
+    )]
+    pub winner: AccountInfo<'info>,
+
+    /// Whoever runs settlement; fronts rent for missing token accounts, which
+    /// is repaid, and is paid the keeper tip on a sale
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(address = auction.nft_mint)]
+    pub nft_mint: Account<'info, Mint>,
+
+    #[account(address = auction.payment_mint)]
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = auction
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = payment_mint,
+        associated_token::authority = auction
+    )]
+    pub bid_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: Winner's NFT ATA, created by the keeper on a sale if missing
+    #[account(
+        mut,
+        address = associated_token::get_associated_token_address(&winner.key(), &auction.nft_mint)
+    )]
+    pub winner_nft_account: AccountInfo<'info>,
+
+    /// CHECK: Winner's payment ATA, created by the keeper for a refund if missing
+    #[account(
+        mut,
+        address = associated_token::get_associated_token_address(&winner.key(), &auction.payment_mint)
+    )]
+    pub winner_payment_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == auction.nft_mint,
+        constraint = seller_nft_account.owner == seller.key()
+    )]
+    pub seller_nft_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Seller's payment ATA, created by the keeper on a sale if missing
+    #[account(
+        mut,
+        address = associated_token::get_associated_token_address(&seller.key(), &auction.payment_mint)
+    )]
+    pub seller_payment_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = keeper_payment_account.mint == auction.payment_mint,
+        constraint = keeper_payment_account.owner == keeper.key()
+    )]
+    pub keeper_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == auction.payment_mint,
+        constraint = treasury_token_account.owner == marketplace.treasury
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Metadata PDA of the auctioned mint, empty if the mint has no
+    /// metadata; creator token accounts for royalties follow as remaining
+    /// accounts in metadata order
+    #[account(
+        seeds = [b"metadata", mpl_token_metadata::ID.as_ref(), auction.nft_mint.as_ref()],
+        bump,
+        seeds::program = mpl_token_metadata::ID
+    )]
+    pub nft_metadata: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelTokenAuction<'info> {
+    #[account(
+        seeds = [b"marketplace", marketplace.creator.as_ref(), marketplace.name.as_bytes()],
+        bump = marketplace.bump
+    )]
+    pub marketplace: Account<'info, Marketplace>,
+
+    #[account(
+        mut,
+        seeds = [b"token_auction", marketplace.key().as_ref(), auction.nft_mint.as_ref()],
+        bump = auction.bump,
+        has_one = marketplace @ MarketplaceError::MarketplaceMismatch,
+        constraint = auction.seller == seller.key(),
+        close = seller
+    )]
+    pub auction: Account<'info, TokenAuction>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_nft_account.mint == auction.nft_mint,
+        constraint = escrow_nft_account.owner == auction.key()
+    )]
+    pub escrow_nft_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = bid_vault.mint == auction.payment_mint,
+        constraint = bid_vault.owner == auction.key()
+    )]
+    pub bid_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == auction.nft_mint,
+        constraint = seller_nft_account.owner == seller.key()
+    )]
+    pub seller_nft_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTokenBidRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"token_bid_refund", bid_refund.auction.as_ref(), bidder.key().as_ref()],
+        bump = bid_refund.bump,
+        constraint = bid_refund.bidder == bidder.key(),
+        close = bidder
+    )]
+    pub bid_refund: Account<'info, TokenBidRefund>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    #[account(address = bid_refund.payment_mint)]
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = payment_mint,
+        associated_token::authority = bid_refund
+    )]
+    pub refund_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        associated_token::mint = payment_mint,
+        associated_token::authority = bidder
+    )]
+    pub bidder_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
@@ -83,7 +83,6 @@ pub mod token_staking {
         );
     
         // Get pool data before mutable borrow
-        let pool_key = ctx.accounts.pool.key();
         let staking_mint = ctx.accounts.pool.staking_mint;
         let reward_rate = ctx.accounts.pool.reward_rate;
         let pool_bump = ctx.accounts.pool.bump;